@@ -0,0 +1,392 @@
+use {
+    std::sync::{Arc, RwLock},
+    r3vi::{
+        view::{OuterViewPort, singleton::*, sequence::*}
+    },
+    nested::{
+        type_system::{Context, ReprTree},
+        editors::list::{ListCursorMode, ListEditor},
+        tree::{NestedNode, TreeNav, TreeCursor, TreeNavResult},
+        commander::ObjCommander
+    },
+    crate::parse::{Word, WordPart}
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+fn char_of(node: &NestedNode) -> Option<char> {
+    node.get_data_view::<dyn SingletonView<Item = Option<char>>>(vec![].into_iter()).get()
+}
+
+fn is_type(ctx: &Arc<RwLock<Context>>, node: &NestedNode, typename: &str) -> bool {
+    node.data.read().unwrap().get_type().clone() == Context::parse(ctx, typename)
+}
+
+/// true for a `NAME=value` node in front of the command
+pub fn is_assignment(ctx: &Arc<RwLock<Context>>, node: &NestedNode) -> bool {
+    is_type(ctx, node, "EnvAssignment")
+}
+
+/// chars which open a nested node inside a word and its type
+pub fn word_openers() -> Vec<(char, &'static str)> {
+    vec![
+        ('$', "shell::Substitution"),
+        ('\'', "shell::SingleQuote"),
+        ('"', "shell::DoubleQuote")
+    ]
+}
+
+/// the closing char of a quote node
+fn closer_of(ctx: &Arc<RwLock<Context>>, node: &NestedNode) -> Option<char> {
+    if is_type(ctx, node, "shell::SingleQuote") {
+        Some('\'')
+    } else if is_type(ctx, node, "shell::DoubleQuote") {
+        Some('"')
+    } else {
+        None
+    }
+}
+
+/// the parts of a `ProcessArg` or quote node,
+/// runs of chars become literals
+fn word_parts(ctx: &Arc<RwLock<Context>>, node: &NestedNode) -> Vec<WordPart> {
+    let items = node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Char>"].into_iter());
+
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+
+    for item in (0..items.len().unwrap_or(0)).filter_map(|i| items.get(&i)) {
+        let part =
+            if is_type(ctx, &item, "shell::Substitution") {
+                let text = crate::substitution::substitution_text(&item);
+                let name = crate::substitution::variable_name(&text);
+                if name.len() == 0 {
+                    // a lone `$`
+                    literal.push('$');
+                    literal.push_str(&text);
+                    continue;
+                }
+                WordPart::Variable(name.into())
+            } else if is_type(ctx, &item, "shell::SingleQuote") {
+                WordPart::SingleQuoted(word_parts(ctx, &item).iter().map(|part| match part {
+                    WordPart::Literal(l) => l.as_str(),
+                    _ => ""
+                }).collect())
+            } else if is_type(ctx, &item, "shell::DoubleQuote") {
+                WordPart::DoubleQuoted(word_parts(ctx, &item))
+            } else {
+                if let Some(c) = char_of(&item) {
+                    literal.push(c);
+                }
+                continue;
+            };
+
+        if literal.len() > 0 {
+            parts.push(WordPart::Literal(std::mem::take(&mut literal)));
+        }
+        parts.push(part);
+    }
+
+    if literal.len() > 0 {
+        parts.push(WordPart::Literal(literal));
+    }
+
+    parts
+}
+
+/// the parts of a `ProcessArg` node
+pub fn arg_word(ctx: &Arc<RwLock<Context>>, arg: &NestedNode) -> Word {
+    Word { pos: 0, parts: word_parts(ctx, arg) }
+}
+
+/// whether the cursor of `list` is inside a quote node
+pub fn cursor_in_quote(ctx: &Arc<RwLock<Context>>, list: &ListEditor) -> bool {
+    let addr = list.get_cursor().tree_addr;
+    let mut node = match addr.first() {
+        Some(idx) if *idx >= 0 && (*idx as usize) < list.data.len() => list.data.get(*idx as usize),
+        _ => { return false; }
+    };
+
+    for idx in addr[1..].iter() {
+        if closer_of(ctx, &node).is_some() {
+            return true;
+        }
+
+        let next = match crate::process::arg_list(&node) {
+            Some(list) => {
+                let list = list.read().unwrap();
+                if *idx >= 0 && (*idx as usize) < list.data.len() {
+                    list.data.get(*idx as usize)
+                } else {
+                    return false;
+                }
+            }
+            None => { return false; }
+        };
+        node = next;
+    }
+
+    false
+}
+
+/// passes `cmd_obj` to the item at the cursor of `list`,
+/// bypassing the split char of its controller
+pub fn send_to_item(list: &Arc<RwLock<ListEditor>>, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+    let list = list.read().unwrap();
+    match list.get_cursor().tree_addr.first() {
+        Some(idx) if *idx >= 0 && (*idx as usize) < list.data.len() => {
+            let mut item = list.data.get(*idx as usize);
+            drop(list);
+            item.send_cmd_obj(cmd_obj)
+        }
+        _ => TreeNavResult::Exit
+    }
+}
+
+/// the chars which, typed into a `ProcessArg`, reproduce `parts`
+fn keys(parts: &Vec<WordPart>, quoted: bool, text: &mut String) {
+    for (i, part) in parts.iter().enumerate() {
+        match part {
+            WordPart::Literal(l) => {
+                for c in l.chars() {
+                    match (quoted, c) {
+                        // chars which would open a node or split the argument
+                        (true, '"') | (true, '$') => { text.push('"'); text.push('\''); text.push(c); text.push('\''); text.push('"'); }
+                        (false, '\'') => { text.push('"'); text.push(c); text.push('"'); }
+                        (false, '"') | (false, '$') | (false, ' ') | (false, '|') => { text.push('\''); text.push(c); text.push('\''); }
+                        _ => text.push(c)
+                    }
+                }
+            }
+            WordPart::SingleQuoted(l) => {
+                if quoted {
+                    keys(&vec![WordPart::Literal(l.clone())], quoted, text);
+                } else {
+                    text.push('\'');
+                    text.push_str(l);
+                    text.push('\'');
+                }
+            }
+            WordPart::DoubleQuoted(inner) => {
+                if quoted {
+                    keys(inner, quoted, text);
+                } else {
+                    text.push('"');
+                    keys(inner, true, text);
+                    text.push('"');
+                }
+            }
+            WordPart::Variable(name) => {
+                // braces are only needed if a name char follows a name,
+                // digits and special parameters end after one char
+                let braced = name.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic()) && match parts.get(i + 1) {
+                    Some(WordPart::Literal(l)) => l.starts_with(crate::parse::is_name_char),
+                    _ => false
+                };
+                if braced {
+                    text.push_str(&format!("${{{}}}", name));
+                } else {
+                    text.push('$');
+                    text.push_str(name);
+                }
+            }
+        }
+    }
+}
+
+fn type_word(ctx: &Arc<RwLock<Context>>, node: &mut NestedNode, word: &Word) {
+    let mut text = String::new();
+    keys(&word.parts, false, &mut text);
+
+    node.goto(TreeCursor::home());
+    for c in text.chars() {
+        node.send_cmd_obj(ReprTree::from_char(ctx, c));
+    }
+    node.goto(TreeCursor::none());
+}
+
+/// a new `ProcessArg` node holding `word`
+pub fn make_arg_node(
+    ctx: &Arc<RwLock<Context>>,
+    word: &Word,
+    depth: OuterViewPort<dyn SingletonView<Item = usize>>
+) -> NestedNode {
+    let mut node = Context::make_node(ctx, Context::parse(ctx, "ProcessArg"), depth).unwrap();
+    type_word(ctx, &mut node, word);
+    node
+}
+
+/// a new `EnvAssignment` node holding `name=value`,
+/// whose value may contain substitutions & quotes like an argument
+pub fn make_assignment_node(
+    ctx: &Arc<RwLock<Context>>,
+    name: &str,
+    value: &Word,
+    depth: OuterViewPort<dyn SingletonView<Item = usize>>
+) -> NestedNode {
+    let mut node = Context::make_node(ctx, Context::parse(ctx, "EnvAssignment"), depth).unwrap();
+    ArgCommander::for_node(&mut node, word_openers(), None);
+    type_word(ctx, &mut node, &Word::assignment(name, value));
+    node
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// wraps the controller of a `ProcessArg` or quote, so that some chars
+/// open a nested node (e.g. `$` a `shell::Substitution`).
+/// chars typed inside go to the nested node until it returns `Exit`.
+/// a quote returns `Exit` on its closing char, which is then consumed.
+pub struct ArgCommander {
+    ctx: Arc<RwLock<Context>>,
+    list: Arc<RwLock<ListEditor>>,
+    inner: Option<Arc<RwLock<dyn ObjCommander + Send + Sync>>>,
+
+    /// char which opens a node and its type
+    openers: Vec<(char, &'static str)>,
+
+    /// char which ends this node
+    closer: Option<char>,
+
+    /// depth of the node, nested nodes are one deeper
+    depth: OuterViewPort<dyn SingletonView<Item = usize>>,
+}
+
+impl ArgCommander {
+    pub fn for_node(node: &mut NestedNode, openers: Vec<(char, &'static str)>, closer: Option<char>) {
+        if let Some(list) = node.get_edit::<ListEditor>() {
+            let commander = ArgCommander {
+                ctx: node.ctx.clone(),
+                list,
+                inner: node.cmd.get(),
+                openers,
+                closer,
+                depth: node.depth.clone()
+            };
+            node.cmd.set(Some(Arc::new(RwLock::new(commander)) as Arc<RwLock<dyn ObjCommander + Send + Sync>>));
+        }
+    }
+
+    fn forward(&mut self, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+        match self.inner.as_ref() {
+            Some(inner) => inner.write().unwrap().send_cmd_obj(cmd_obj),
+            None => TreeNavResult::Exit
+        }
+    }
+
+    fn send_char(&mut self, c: char, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+        let cursor = self.list.read().unwrap().get_cursor();
+
+        match cursor.tree_addr.as_slice() {
+            // inside a nested node
+            [idx, _, ..] if *idx >= 0 => {
+                let idx = *idx as usize;
+                let mut child = self.list.read().unwrap().data.get(idx);
+
+                match child.send_cmd_obj(cmd_obj.clone()) {
+                    TreeNavResult::Continue => TreeNavResult::Continue,
+                    TreeNavResult::Exit => {
+                        self.list.write().unwrap().goto(TreeCursor {
+                            leaf_mode: ListCursorMode::Insert,
+                            tree_addr: vec![idx as isize + 1]
+                        });
+
+                        if closer_of(&self.ctx, &child) == Some(c) {
+                            TreeNavResult::Continue
+                        } else {
+                            // the char belongs behind the nested node
+                            self.send_char(c, cmd_obj)
+                        }
+                    }
+                }
+            }
+
+            [idx] if *idx >= 0 && self.closer == Some(c) => TreeNavResult::Exit,
+
+            [idx] if *idx >= 0 => {
+                let opener = self.openers.iter().find(|(o, _)| *o == c).map(|(_, typename)| *typename);
+                match opener {
+                    Some(typename) => {
+                        let node = Context::make_node(
+                            &self.ctx,
+                            Context::parse(&self.ctx, typename),
+                            self.depth.map(|d| d + 1)
+                        ).unwrap();
+
+                        let mut list = self.list.write().unwrap();
+                        list.data.insert(*idx as usize, node);
+                        list.goto(TreeCursor {
+                            leaf_mode: ListCursorMode::Insert,
+                            tree_addr: vec![*idx, 0]
+                        });
+                        TreeNavResult::Continue
+                    }
+                    None => self.forward(cmd_obj)
+                }
+            }
+
+            _ => self.forward(cmd_obj)
+        }
+    }
+}
+
+impl ObjCommander for ArgCommander {
+    fn send_cmd_obj(&mut self, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+        let co = cmd_obj.read().unwrap();
+        let char_value =
+            if co.get_type().clone() == Context::parse(&self.ctx, "Char") {
+                co.get_view::<dyn SingletonView<Item = char>>().map(|v| v.get())
+            } else {
+                None
+            };
+        drop(co);
+
+        match char_value {
+            Some(c) => self.send_char(c, cmd_obj),
+            None => self.forward(cmd_obj)
+        }
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// wraps the controller of a list of (lists of) `ProcessArg`s,
+/// so that its split char is literal while the cursor is inside a quote
+pub struct QuotedSplit {
+    ctx: Arc<RwLock<Context>>,
+    list: Arc<RwLock<ListEditor>>,
+    inner: Option<Arc<RwLock<dyn ObjCommander + Send + Sync>>>,
+    split: char,
+}
+
+impl QuotedSplit {
+    pub fn for_node(node: &mut NestedNode, split: char) {
+        if let Some(list) = node.get_edit::<ListEditor>() {
+            let commander = QuotedSplit {
+                ctx: node.ctx.clone(),
+                list,
+                inner: node.cmd.get(),
+                split
+            };
+            node.cmd.set(Some(Arc::new(RwLock::new(commander)) as Arc<RwLock<dyn ObjCommander + Send + Sync>>));
+        }
+    }
+}
+
+impl ObjCommander for QuotedSplit {
+    fn send_cmd_obj(&mut self, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+        let co = cmd_obj.read().unwrap();
+        let is_split =
+            co.get_type().clone() == Context::parse(&self.ctx, "Char")
+            && co.get_view::<dyn SingletonView<Item = char>>().map(|v| v.get()) == Some(self.split);
+        drop(co);
+
+        if is_split && cursor_in_quote(&self.ctx, &self.list.read().unwrap()) {
+            send_to_item(&self.list, cmd_obj)
+        } else {
+            match self.inner.as_ref() {
+                Some(inner) => inner.write().unwrap().send_cmd_obj(cmd_obj),
+                None => TreeNavResult::Exit
+            }
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use {
+    std::sync::{Arc, RwLock},
+    std::sync::atomic::{AtomicI32, AtomicBool, Ordering}
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// the part of the shell state that builtins operate on
+pub struct Shell {
+    pub cwd: String,
+    pub stdout: String,
+}
+
+impl Shell {
+    pub fn new(cwd: Option<String>) -> Self {
+        Shell {
+            cwd: cwd.unwrap_or_else(||
+                std::env::current_dir().map(|p| p.to_string_lossy().into_owned()).unwrap_or("/".into())
+            ),
+            stdout: String::new(),
+        }
+    }
+
+    pub fn print(&mut self, line: &str) {
+        self.stdout.push_str(line);
+        self.stdout.push('\n');
+    }
+}
+
+/// a command which is run inside the shell process
+pub trait Builtin: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// node type of the argument editor, e.g. `Path` for `cd`
+    fn arg_type(&self) -> &'static str {
+        "ProcessArg"
+    }
+
+    /// types of stdin (`>0`), stdout (`<1`) and the argument (`$1`)
+    /// in typedb notation
+    fn signature(&self) -> &'static str {
+        ">0: None\n<1: None"
+    }
+
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String>;
+}
+
+static BUILTINS: RwLock<Vec<Arc<dyn Builtin>>> = RwLock::new(Vec::new());
+static HISTORY: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// directories saved by `pushd`, the most recent first
+static DIR_STACK: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+static EXIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+pub fn register(builtin: Arc<dyn Builtin>) {
+    let mut builtins = BUILTINS.write().unwrap();
+    builtins.retain(|b| b.name() != builtin.name());
+    builtins.push(builtin);
+}
+
+pub fn get(name: &str) -> Option<Arc<dyn Builtin>> {
+    BUILTINS.read().unwrap().iter().find(|b| b.name() == name).cloned()
+}
+
+pub fn names() -> Vec<&'static str> {
+    BUILTINS.read().unwrap().iter().map(|b| b.name()).collect()
+}
+
+/// runs `argv` if it names a builtin
+pub fn run(argv: &[String], sh: &mut Shell) -> Option<Result<(), String>> {
+    let builtin = get(argv.first()?)?;
+    Some(builtin.run(&argv[1..], sh))
+}
+
+/// the type of `item` (`>0` or `<1`) from a builtin's signature
+pub fn signature_type(name: &str, item: &str) -> Option<String> {
+    let builtin = get(name)?;
+    builtin.signature().lines()
+        .find_map(|line| line.strip_prefix(item)?.strip_prefix(": "))
+        .map(|t| t.to_string())
+}
+
+pub fn add_history(line: String) {
+    HISTORY.write().unwrap().push(line);
+}
+
+pub fn dir_stack() -> Vec<String> {
+    DIR_STACK.read().unwrap().clone()
+}
+
+pub fn set_dir_stack(stack: Vec<String>) {
+    *DIR_STACK.write().unwrap() = stack;
+}
+
+/// the exit code once the `exit` builtin was run
+pub fn exit_requested() -> Option<i32> {
+    if EXIT_REQUESTED.load(Ordering::Relaxed) {
+        Some(EXIT_CODE.load(Ordering::Relaxed))
+    } else {
+        None
+    }
+}
+
+pub fn init() {
+    register(Arc::new(Cd));
+    register(Arc::new(Pwd));
+    register(Arc::new(Export));
+    register(Arc::new(Unset));
+    register(Arc::new(Exit));
+    register(Arc::new(Type));
+    register(Arc::new(Alias));
+    register(Arc::new(Unalias));
+    register(Arc::new(History));
+    register(Arc::new(Jobs));
+    register(Arc::new(Fg));
+    register(Arc::new(Bg));
+    register(Arc::new(Pushd));
+    register(Arc::new(Popd));
+    register(Arc::new(Dirs));
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// resolves `.` and `..` without looking at the filesystem, like a logical `cd`
+fn normalize(path: &std::path::Path) -> std::path::PathBuf {
+    use std::path::Component;
+
+    let mut out = std::path::PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::ParentDir => { out.pop(); }
+            Component::Normal(name) => { out.push(name); }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    out
+}
+
+fn home() -> Result<String, String> {
+    crate::env::get("HOME").ok_or_else(|| "HOME not set".to_string())
+}
+
+struct Cd;
+impl Builtin for Cd {
+    fn name(&self) -> &'static str { "cd" }
+    fn arg_type(&self) -> &'static str { "Path" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: None\n$1: Directory~Path~<Seq Char>" }
+
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String> {
+        let mut physical = false;
+        let mut dir = None;
+        for arg in args {
+            match arg.as_str() {
+                "-P" => { physical = true; }
+                "-L" => { physical = false; }
+                _ if dir.is_none() => { dir = Some(arg.clone()); }
+                _ => { return Err("too many arguments".into()); }
+            }
+        }
+
+        // print the new directory when it is not obvious from the argument
+        let mut announce = false;
+
+        let dir = match dir.as_deref() {
+            None => home()?,
+            Some("-") => {
+                announce = true;
+                crate::env::get("OLDPWD").ok_or_else(|| "OLDPWD not set".to_string())?
+            }
+            Some(dir) => crate::path::expand_home(dir).ok_or_else(|| "HOME not set".to_string())?
+        };
+
+        let cwd = std::path::Path::new(&sh.cwd);
+        let mut target = cwd.join(&dir);
+
+        // search CDPATH for plain relative names
+        let is_plain = !dir.starts_with('/') && !dir.starts_with("./") && !dir.starts_with("../")
+            && dir != "." && dir != "..";
+        if is_plain {
+            if let Some(cdpath) = crate::env::get("CDPATH") {
+                for base in cdpath.split(':') {
+                    let candidate = if base.len() == 0 { cwd.join(&dir) } else { std::path::Path::new(base).join(&dir) };
+                    if candidate.is_dir() {
+                        announce |= base.len() > 0;
+                        target = candidate;
+                        break;
+                    }
+                }
+            }
+        }
+
+        let target = if physical {
+            std::fs::canonicalize(&target).map_err(|err| format!("{}: {}", dir, err))?
+        } else {
+            normalize(&target)
+        };
+
+        if !target.is_dir() {
+            return Err(if target.exists() {
+                format!("{}: not a directory", dir)
+            } else {
+                format!("{}: no such file or directory", dir)
+            });
+        }
+
+        std::env::set_current_dir(&target).map_err(|err| format!("{}: {}", dir, err))?;
+
+        let target = target.to_string_lossy().into_owned();
+        crate::env::set("OLDPWD", &sh.cwd);
+        crate::env::set("PWD", &target);
+
+        if announce {
+            sh.print(&target);
+        }
+        sh.cwd = target;
+        Ok(())
+    }
+}
+
+struct Pwd;
+impl Builtin for Pwd {
+    fn name(&self) -> &'static str { "pwd" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: Path~<Seq Char>" }
+
+    fn run(&self, _args: &[String], sh: &mut Shell) -> Result<(), String> {
+        let cwd = sh.cwd.clone();
+        sh.print(&cwd);
+        Ok(())
+    }
+}
+
+struct Export;
+impl Builtin for Export {
+    fn name(&self) -> &'static str { "export" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: <Seq Char>" }
+
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String> {
+        if args.len() == 0 {
+            for (name, value) in crate::env::vars() {
+                sh.print(&format!("export {}={}", name, crate::export::quote(&value)));
+            }
+        }
+
+        for arg in args {
+            match crate::parse::split_assignment(arg) {
+                Some((name, value)) => crate::env::set(name, value),
+                None if crate::env::get(arg).is_some() => {}
+                None => { return Err(format!("{}: not a valid assignment", arg)); }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Unset;
+impl Builtin for Unset {
+    fn name(&self) -> &'static str { "unset" }
+
+    fn run(&self, args: &[String], _sh: &mut Shell) -> Result<(), String> {
+        for name in args {
+            crate::env::unset(name);
+        }
+        Ok(())
+    }
+}
+
+struct Exit;
+impl Builtin for Exit {
+    fn name(&self) -> &'static str { "exit" }
+
+    fn run(&self, args: &[String], _sh: &mut Shell) -> Result<(), String> {
+        let code = match args.first() {
+            Some(code) => code.parse::<i32>().map_err(|_| format!("{}: numeric argument required", code))?,
+            None => 0
+        };
+
+        EXIT_CODE.store(code, Ordering::Relaxed);
+        EXIT_REQUESTED.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+struct Type;
+impl Builtin for Type {
+    fn name(&self) -> &'static str { "type" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: <Seq Char>" }
+
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String> {
+        let mut missing = Vec::new();
+
+        for name in args {
+            match crate::which::resolve(name, Some(&sh.cwd)) {
+                crate::which::Resolution::Builtin =>
+                    sh.print(&format!("{} is a shell builtin", name)),
+                crate::which::Resolution::Alias(value) =>
+                    sh.print(&format!("{} is aliased to `{}`", name, value)),
+                crate::which::Resolution::Executable(path) =>
+                    sh.print(&format!("{} is {}", name, path.display())),
+                _ => missing.push(name.clone())
+            }
+        }
+
+        if missing.len() > 0 {
+            Err(format!("{}: not found", missing.join(", ")))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct Alias;
+impl Builtin for Alias {
+    fn name(&self) -> &'static str { "alias" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: <Seq Char>" }
+
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String> {
+        if args.len() == 0 {
+            for (name, value) in crate::which::aliases() {
+                sh.print(&format!("alias {}={}", name, crate::export::quote(&value)));
+            }
+        }
+
+        for arg in args {
+            match crate::parse::split_assignment(arg) {
+                Some((name, value)) => crate::which::set_alias(name, value),
+                None => match crate::which::get_alias(arg) {
+                    Some(value) => sh.print(&format!("alias {}={}", arg, crate::export::quote(&value))),
+                    None => { return Err(format!("{}: not found", arg)); }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Unalias;
+impl Builtin for Unalias {
+    fn name(&self) -> &'static str { "unalias" }
+
+    fn run(&self, args: &[String], _sh: &mut Shell) -> Result<(), String> {
+        let missing : Vec<&str> = args.iter()
+            .filter(|name| crate::which::remove_alias(name).is_none())
+            .map(|name| name.as_str())
+            .collect();
+
+        if missing.len() > 0 {
+            Err(format!("{}: not found", missing.join(", ")))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct History;
+impl Builtin for History {
+    fn name(&self) -> &'static str { "history" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: <Seq Char>" }
+
+    fn run(&self, _args: &[String], sh: &mut Shell) -> Result<(), String> {
+        for (i, line) in HISTORY.read().unwrap().iter().enumerate() {
+            sh.stdout.push_str(&format!("{:>5}  {}\n", i + 1, line));
+        }
+        Ok(())
+    }
+}
+
+struct Jobs;
+impl Builtin for Jobs {
+    fn name(&self) -> &'static str { "jobs" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: <Seq Char>" }
+
+    fn run(&self, _args: &[String], sh: &mut Shell) -> Result<(), String> {
+        for job in crate::job::list() {
+            sh.print(&format!("{}", job));
+        }
+        Ok(())
+    }
+}
+
+fn resume(args: &[String], sh: &mut Shell, foreground: bool) -> Result<(), String> {
+    let job = crate::job::resolve(args.first().map(|s| s.as_str()))
+        .and_then(|job| crate::job::resume(job.id, foreground))?;
+    sh.print(&format!("{}", job));
+    Ok(())
+}
+
+struct Fg;
+impl Builtin for Fg {
+    fn name(&self) -> &'static str { "fg" }
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String> {
+        resume(args, sh, true)
+    }
+}
+
+struct Bg;
+impl Builtin for Bg {
+    fn name(&self) -> &'static str { "bg" }
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String> {
+        resume(args, sh, false)
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+fn tilde(dir: &str) -> String {
+    match crate::env::get("HOME") {
+        Some(home) if home.len() > 1 && dir.starts_with(&home) => format!("~{}", &dir[home.len()..]),
+        _ => dir.to_string()
+    }
+}
+
+fn print_dirs(sh: &mut Shell) {
+    let line = std::iter::once(sh.cwd.clone())
+        .chain(dir_stack().into_iter())
+        .map(|dir| tilde(&dir))
+        .collect::<Vec<_>>()
+        .join(" ");
+    sh.print(&line);
+}
+
+/// `+n` counts from the left of the `dirs` list
+fn stack_index(arg: &str) -> Option<usize> {
+    arg.strip_prefix('+')?.parse::<usize>().ok()
+}
+
+struct Pushd;
+impl Builtin for Pushd {
+    fn name(&self) -> &'static str { "pushd" }
+    fn arg_type(&self) -> &'static str { "Path" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: <Seq Char>\n$1: Directory~Path~<Seq Char>" }
+
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String> {
+        let mut stack = dir_stack();
+        let old_cwd = sh.cwd.clone();
+
+        match args.first() {
+            None => {
+                if stack.len() == 0 {
+                    return Err("no other directory".into());
+                }
+                Cd.run(&[ stack[0].clone() ], sh)?;
+                stack[0] = old_cwd;
+            }
+            Some(arg) if stack_index(arg).is_some() => {
+                let n = stack_index(arg).unwrap();
+                let mut dirs = vec![ old_cwd ];
+                dirs.extend(stack);
+                if n >= dirs.len() {
+                    return Err(format!("{}: directory stack index out of range", arg));
+                }
+                dirs.rotate_left(n);
+                Cd.run(&[ dirs[0].clone() ], sh)?;
+                stack = dirs.split_off(1);
+            }
+            Some(dir) => {
+                Cd.run(&[ dir.clone() ], sh)?;
+                stack.insert(0, old_cwd);
+            }
+        }
+
+        set_dir_stack(stack);
+        print_dirs(sh);
+        Ok(())
+    }
+}
+
+struct Popd;
+impl Builtin for Popd {
+    fn name(&self) -> &'static str { "popd" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: <Seq Char>" }
+
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String> {
+        let mut stack = dir_stack();
+        if stack.len() == 0 {
+            return Err("directory stack empty".into());
+        }
+
+        match args.first().map(|a| a.as_str()) {
+            None | Some("+0") => {
+                Cd.run(&[ stack[0].clone() ], sh)?;
+                stack.remove(0);
+            }
+            Some(arg) => match stack_index(arg) {
+                Some(n) if n >= 1 && n <= stack.len() => { stack.remove(n - 1); }
+                _ => { return Err(format!("{}: directory stack index out of range", arg)); }
+            }
+        }
+
+        set_dir_stack(stack);
+        print_dirs(sh);
+        Ok(())
+    }
+}
+
+struct Dirs;
+impl Builtin for Dirs {
+    fn name(&self) -> &'static str { "dirs" }
+    fn signature(&self) -> &'static str { ">0: None\n<1: <Seq Char>" }
+
+    fn run(&self, args: &[String], sh: &mut Shell) -> Result<(), String> {
+        match args.first().map(|a| a.as_str()) {
+            None => print_dirs(sh),
+            Some("-c") => set_dir_stack(Vec::new()),
+            Some("-v") => {
+                let dirs = std::iter::once(sh.cwd.clone()).chain(dir_stack().into_iter()).collect::<Vec<_>>();
+                for (i, dir) in dirs.iter().enumerate() {
+                    sh.print(&format!("{:>2}  {}", i, tilde(dir)));
+                }
+            }
+            Some(arg) => { return Err(format!("{}: invalid option", arg)); }
+        }
+        Ok(())
+    }
+}
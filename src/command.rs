@@ -6,7 +6,7 @@ use {
             singleton::*,
             sequence::*,
         },
-        buffer::{singleton::*, index_hashmap::*},
+        buffer::{singleton::*, vec::*, index_hashmap::*},
         projection::decorate_sequence::Separate
     },
     laddertypes::{TypeTerm},
@@ -14,14 +14,14 @@ use {
         editors::{list::{ListEditor, ListCursorMode}, sum::SumEditor},
         terminal::{
             TerminalAtom, TerminalStyle, TerminalView,
-            widgets::ascii_box::AsciiBox, TerminalEvent, make_label
+            widgets::ascii_box::AsciiBox, TerminalEvent, make_label, TerminalProjections
         },
         tree::{NestedNode, TreeNav, TreeCursor, TreeNavResult},
         type_system::{Context, ReprTree},
         commander::ObjCommander,
         PtySegment
     },
-    std::sync::Arc,
+    std::sync::{Arc, Weak},
     std::sync::RwLock,
     std::io::{Read, Write},
     cgmath::{Point2, Vector2},
@@ -35,8 +35,14 @@ use {
 #[derive(Clone)]
 enum CommandState {
     Incubator(Arc<RwLock<PipelineLauncher>>),
-    CD(NestedNode),
+
+    /// a builtin whose argument is edited as its own node type
+    BuiltinArg(Arc<dyn crate::builtin::Builtin>, NestedNode),
+
     Pipeline(Arc<RwLock<PipelineLauncher>>),
+
+    /// the n-th entry of the directory stack, edited in place
+    DirStack(usize),
 }
 
 pub struct Command {
@@ -49,9 +55,24 @@ pub struct Command {
 
     cwd: String,
 
-    sum_editor: NestedNode
+    /// `Path` nodes of the directory stack, shown next to the cwd.
+    /// edits are written back when the entry is left
+    dirstack: VecBuffer<NestedNode>,
+    dirstack_views: VecBuffer<OuterViewPort<dyn TerminalView>>,
+
+    /// candidates of an ambiguous completion, shown below the editor
+    completions: VecBuffer<OuterViewPort<dyn TerminalView>>,
+
+    sum_editor: NestedNode,
+
+    /// the node's commander, which takes the keys of its job after `fg`
+    this: Weak<RwLock<Command>>,
+    job_id: Option<usize>,
 }
 
+/// completion candidates beyond this are only counted
+const MAX_COMPLETIONS: usize = 8;
+
 impl Into<NestedNode> for Command {
     fn into(self) -> NestedNode {
         self.into_node()
@@ -78,10 +99,13 @@ impl Command {
     }
 
     pub fn into_node(self) -> NestedNode {
-        self.sum_editor.clone()
+        let node = self.sum_editor.clone()
             .set_view(self.comp_port.outer())
-            .set_diag(self.sum_editor.get_edit::<SumEditor>().unwrap().read().unwrap().editors[0].diag.clone().unwrap())
-            .set_cmd(Arc::new(RwLock::new(self)))
+            .set_diag(self.sum_editor.get_edit::<SumEditor>().unwrap().read().unwrap().editors[0].diag.clone().unwrap());
+
+        let command = Arc::new(RwLock::new(self));
+        command.write().unwrap().this = Arc::downgrade(&command);
+        node.set_cmd(command)
     }
 
     pub fn new(ctx: Arc<RwLock<Context>>, cwd: String) -> Self {
@@ -97,6 +121,22 @@ impl Command {
         }
         cwd_node.goto(TreeCursor::none());
 
+        let dirstack_views = VecBuffer::new();
+        let completions = VecBuffer::new();
+
+        let mut prompt = IndexBuffer::new();
+        prompt.insert_iter(vec![
+            (Point2::new(0, 0), cwd_node.get_view()
+             .map_item(|_idx, x| x.add_style_back(nested::utils::color::fg_style_from_depth(1)))
+            ),
+            (Point2::new(1, 0), dirstack_views.get_port()
+             .to_sequence()
+             .to_grid_horizontal()
+             .flatten()
+             .map_item(|_idx, x| x.add_style_back(TerminalStyle::fg_color((90, 90, 90))))
+            )
+        ]);
+
         let mut grid = IndexBuffer::new();
         let mut incubator_node = Context::make_node(&ctx, Context::parse(&ctx, "Pipeline"), SingletonBuffer::new(2).get_port()).unwrap();
         let mut path_node = Context::make_node(&ctx, Context::parse(&ctx, "Path"), SingletonBuffer::new(2).get_port()).unwrap();
@@ -111,15 +151,21 @@ impl Command {
 
         grid.insert_iter(
             vec![
-                (Point2::new(0, 0), cwd_node.get_view()
-                 .map_item(|_idx, x| x.add_style_back(nested::utils::color::fg_style_from_depth(1)))
-                ),
-                (Point2::new(1, 0), nested::terminal::make_label("$ ")),
-                (Point2::new(3, 0), sum_editor.pty_view()),
-                (Point2::new(3, 1),
+                (Point2::new(0, 0),
                  incubator_node.get_edit::<PipelineLauncher>().unwrap()
                  .read().unwrap()
-                 .get_type_view())
+                 .status_view()),
+                (Point2::new(1, 0), prompt.get_port().flatten()),
+                (Point2::new(2, 0), nested::terminal::make_label("$ ")),
+                (Point2::new(4, 0), sum_editor.pty_view()),
+                (Point2::new(4, 1),
+                 incubator_node.get_edit::<PipelineLauncher>().unwrap()
+                 .read().unwrap()
+                 .get_type_view()),
+                (Point2::new(4, 2), completions.get_port()
+                 .to_sequence()
+                 .to_grid_vertical()
+                 .flatten())
             ]
         );
 
@@ -131,7 +177,7 @@ impl Command {
         compositor.write().unwrap().push(
             incubator_node.get_edit::<PipelineLauncher>().unwrap()
                 .read().unwrap()
-                .box_port.outer()
+                .output_view()
                 .map_item(|_idx, x| x.add_style_back(TerminalStyle::fg_color((30, 80, 50))))
         );
         compositor.write().unwrap().push(
@@ -139,49 +185,299 @@ impl Command {
                 .map_item(|_idx, x| x.add_style_back(TerminalStyle::fg_color((220, 220, 0)))),
         );
         
-        Command {
+        incubator_node.get_edit::<PipelineLauncher>().unwrap()
+            .write().unwrap()
+            .cwd = Some(cwd.clone());
+
+        let mut command = Command {
             ctx: ctx.clone(),
             grid,
             cwd,
             cwd_node,
             comp_port,
             state: CommandState::Incubator(incubator_node.get_edit::<PipelineLauncher>().unwrap()),
-            sum_editor: sum_editor.into_node(ctx)
+            dirstack: VecBuffer::new(),
+            dirstack_views,
+            completions,
+            sum_editor: sum_editor.into_node(ctx),
+            this: Weak::new(),
+            job_id: None
+        };
+        command.sync_dirstack();
+        command
+    }
+
+    /// rebuilds the directory stack strip from the shell's stack
+    fn sync_dirstack(&mut self) {
+        self.dirstack.clear();
+        self.dirstack_views.clear();
+
+        for dir in crate::builtin::dir_stack() {
+            let mut path_node = Context::make_node(&self.ctx, Context::parse(&self.ctx, "Path"), SingletonBuffer::new(2).get_port()).unwrap();
+            crate::path::highlight_segments(&mut path_node, Some(self.cwd.clone()), Some(crate::path::FileKind::Directory));
+
+            path_node.goto(TreeCursor::home());
+            for c in dir.chars() {
+                path_node.send_cmd_obj(ReprTree::from_char(&self.ctx, c));
+            }
+            path_node.goto(TreeCursor::none());
+
+            let mut entry = IndexBuffer::new();
+            entry.insert_iter(vec![
+                (Point2::new(0, 0), make_label(" ")),
+                (Point2::new(1, 0), path_node.get_view())
+            ]);
+
+            self.dirstack_views.push(entry.get_port().flatten());
+            self.dirstack.push(path_node);
         }
     }
 
-    
+    /// edits the n-th entry of the directory stack
+    fn edit_dirstack(&mut self, n: usize) {
+        let mut entry = self.dirstack.get(n);
+
+        self.grid.insert(Point2::new(3, 0), make_label(&format!("+{} ", n)));
+        self.pipeline().write().unwrap().editor.goto(TreeCursor::none());
+
+        let se = self.sum_editor.get_edit::<SumEditor>().unwrap();
+        let mut se = se.write().unwrap();
+        se.editors[1] = entry.clone();
+        se.select(1);
+
+        self.state = CommandState::DirStack(n);
+        entry.goto(TreeCursor::home());
+    }
+
+    /// writes the entries back to the directory stack, emptied ones are
+    /// dropped, and continues with the pipeline
+    fn leave_dirstack(&mut self) {
+        let stack = (0..self.dirstack.len())
+            .map(|i| {
+                let mut dir = crate::path::path_string(&self.dirstack.get(i));
+                if dir.len() > 1 && dir.ends_with('/') {
+                    dir.pop();
+                }
+                dir
+            })
+            .filter(|dir| dir.len() > 0)
+            .collect();
+        crate::builtin::set_dir_stack(stack);
+        self.sync_dirstack();
+        self.state = CommandState::Incubator(self.pipeline());
+
+        let se = self.sum_editor.get_edit::<SumEditor>().unwrap();
+        let mut se = se.write().unwrap();
+        se.editors[1] = Context::make_node(&self.ctx, Context::parse(&self.ctx, "Path"), SingletonBuffer::new(2).get_port()).unwrap();
+
+        self.grid.remove(Point2::new(3, 0));
+        se.select(0);
+        se.goto(TreeCursor::home());
+    }
+
+    /// cwd and directory stack may have been changed by a builtin
+    fn sync_dirs(&mut self) {
+        if let Some(cwd) = self.pipeline().read().unwrap().cwd.clone() {
+            self.set_cwd(cwd);
+        }
+        self.sync_dirstack();
+    }
+
+    /// replaces the pipeline of this command by the parsed shell string
+    pub fn load_str(&mut self, s: &str) -> Result<(), crate::parse::ParseError> {
+        let pipeline = self.pipeline();
+        pipeline.write().unwrap().load_str(s)?;
+        self.state = CommandState::Pipeline(pipeline);
+
+        Ok(())
+    }
+
     pub fn get_cwd_string(&self) -> String {
-        let mut string = String::new();
+        crate::path::path_string(&self.cwd_node)
+    }
+
+    /// shows a new working directory in the prompt
+    fn set_cwd(&mut self, cwd: String) {
+        if cwd == self.cwd {
+            return;
+        }
+
+        let cwd_edit = self.cwd_node.get_edit::<ListEditor>().unwrap();
+        cwd_edit.write().unwrap().data.clear();
+
+        self.cwd_node.goto(TreeCursor::home());
+        for c in cwd.chars() {
+            self.cwd_node.send_cmd_obj(ReprTree::from_char(&self.ctx, c));
+        }
+        self.cwd_node.goto(TreeCursor::none());
 
-        let cd_path = self.cwd_node.get_data_view::<dyn SequenceView<Item = NestedNode>>(
+        self.cwd = cwd;
+    }
+
+    /// shows the candidates of an ambiguous completion
+    fn show_completions(&mut self, items: Vec<(String, (u8, u8, u8))>) {
+        self.completions.clear();
+
+        let n = items.len();
+        for (label, color) in items.into_iter().take(MAX_COMPLETIONS) {
+            self.completions.push(make_label(&label).with_fg_color(color));
+        }
+        if n > MAX_COMPLETIONS {
+            self.completions.push(make_label(&format!("... {} more", n - MAX_COMPLETIONS)).with_fg_color((90, 90, 90)));
+        }
+    }
+
+    /// completes the path segment under the cursor from the directory
+    /// which the preceding segments resolve to
+    fn complete_path(&mut self, arg: &mut NestedNode) {
+        let cursor = arg.get_cursor();
+        if cursor.tree_addr.len() < 2 || cursor.tree_addr[0] < 0 {
+            return;
+        }
+        let idx = cursor.tree_addr[0] as usize;
+
+        let segments : Vec<String> = arg.get_data_view::<dyn SequenceView<Item = NestedNode>>(
             vec![
                 "<List PathSegment>"
             ].into_iter()
-        ).unwrap();
+        ).unwrap()
+            .iter()
+            .map(|segment| crate::path::segment_string(&segment))
+            .collect();
 
-        for segment in cd_path.iter() {
-            let mut node = segment.clone();
-            node.goto(TreeCursor::none());
+        if idx >= segments.len() {
+            return;
+        }
 
-            let segment_view = node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Char>"].into_iter());
+        let prefix = segments[idx].clone();
+        let dir = crate::path::resolve_segments(&self.cwd, &segments[..idx]);
+        let candidates = crate::path::complete_segment(&dir, &prefix);
+
+        let completion = match candidates.len() {
+            0 => { return; }
+            1 => candidates[0].0.clone(),
+            _ => crate::path::common_prefix(candidates.iter().map(|(name, _)| name.as_str()))
+        };
+
+        arg.goto(TreeCursor {
+            leaf_mode: ListCursorMode::Insert,
+            tree_addr: vec![ idx as isize, prefix.chars().count() as isize ]
+        });
+        for c in completion.chars().skip(prefix.chars().count()) {
+            arg.send_cmd_obj(ReprTree::from_char(&self.ctx, c));
+        }
 
-            for k in 0..segment_view.len().unwrap_or(0) {
-                let char_node = segment_view.get(&k).unwrap();
-                let char_view = char_node.get_data_view::<dyn SingletonView<Item = Option<char>>>(vec![].into_iter());
-                if let Some(c) = char_view.get() {
-                    string.push(c);
+        if candidates.len() == 1 {
+            // continue with the next segment
+            if candidates[0].1 == crate::path::FileKind::Directory && idx + 1 == segments.len() {
+                arg.send_cmd_obj(ReprTree::from_char(&self.ctx, '/'));
+            }
+        } else {
+            self.show_completions(
+                candidates.into_iter()
+                    .map(|(name, kind)| match kind {
+                        crate::path::FileKind::Directory => (format!("{}/", name), kind.color()),
+                        kind => (name, kind.color())
+                    })
+                    .collect()
+            );
+        }
+    }
+
+    /// completes the command name or argument of the process under the cursor
+    fn complete_process(&mut self, pipeline: Arc<RwLock<PipelineLauncher>>) -> TreeNavResult {
+        let cursor = pipeline.read().unwrap().editor.get_cursor();
+        if cursor.tree_addr.len() < 3 || cursor.tree_addr[0] < 0 || cursor.tree_addr[1] < 0 {
+            return TreeNavResult::Exit;
+        }
+        let j = cursor.tree_addr[0] as usize;
+        let i = cursor.tree_addr[1] as usize;
+
+        let (words, upstream) = {
+            let p = pipeline.read().unwrap();
+            (
+                p.process_words(j),
+                if j > 0 {
+                    Some(crate::parse::Process::from_words(p.process_words(j - 1)).expand().1)
+                } else {
+                    None
                 }
+            )
+        };
+        if i >= words.len() {
+            return TreeNavResult::Exit;
+        }
+        if words[i].as_assignment().is_some() {
+            return TreeNavResult::Continue;
+        }
+
+        let args : Vec<String> = words.iter().map(|word| word.to_arg()).collect();
+        let prefix = args[i].clone();
+
+        // the command follows the `NAME=value` assignments
+        let n_assignments = words[..i].iter().take_while(|word| word.as_assignment().is_some()).count();
+
+        let candidates =
+            if i == n_assignments {
+                crate::complete::command_names(
+                    &self.ctx,
+                    &prefix,
+                    upstream.as_ref().map(|args| args.as_slice())
+                )
+            } else {
+                crate::complete::arguments(&args[n_assignments..i], &prefix)
+            };
+
+        let completion = match candidates.len() {
+            0 => { return TreeNavResult::Continue; }
+            1 => candidates[0].text.clone(),
+            _ => crate::complete::common_prefix(&candidates)
+        };
+
+        {
+            let mut p = pipeline.write().unwrap();
+            p.editor.goto(TreeCursor {
+                leaf_mode: ListCursorMode::Insert,
+                tree_addr: vec![ j as isize, i as isize, prefix.chars().count() as isize ]
+            });
+            for c in completion.chars().skip(prefix.chars().count()) {
+                p.editor.send_cmd_obj(ReprTree::from_char(&self.ctx, c));
             }
-            string.push('/');
         }
-        
-        string
+
+        if candidates.len() == 1 && candidates[0].slot.is_none() {
+            // go on with the next argument
+            if i + 1 == args.len() {
+                self.send_cmd_obj(ReprTree::from_char(&self.ctx, ' '));
+            }
+        } else {
+            self.show_completions(
+                candidates.iter()
+                    .map(|candidate| (candidate.label(), candidate.color))
+                    .collect()
+            );
+        }
+
+        TreeNavResult::Continue
     }
-}
 
-impl ObjCommander for Command {
-    fn send_cmd_obj(&mut self, obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+    fn pipeline(&self) -> Arc<RwLock<PipelineLauncher>> {
+        self.sum_editor.get_edit::<SumEditor>().unwrap()
+            .read().unwrap()
+            .editors[0].get_edit::<PipelineLauncher>().unwrap()
+    }
+
+    /// lets the keys reach the job of the last launch, also after `fg`
+    fn register_job(&mut self) {
+        let job_id = self.pipeline().read().unwrap().job_id();
+        if let Some(id) = job_id.filter(|_| job_id != self.job_id) {
+            self.job_id = job_id;
+            let input : Weak<RwLock<dyn crate::job::JobInput>> = self.this.clone();
+            crate::job::set_input(id, input);
+        }
+    }
+
+    fn dispatch(&mut self, obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
         let cmd_obj = obj.clone();
         let cmd_obj = cmd_obj.read().unwrap();
         let cmd_type = cmd_obj.get_type().clone();
@@ -208,35 +504,150 @@ impl ObjCommander for Command {
                 None
             };
 
+        // a program in the output box gets all keys
+        if let CommandState::Incubator(pipeline) | CommandState::Pipeline(pipeline) = &self.state {
+            if pipeline.read().unwrap().is_captured() {
+                drop(cmd_obj);
+                return pipeline.write().unwrap().send_cmd_obj(obj);
+            }
+        }
+
+        // completion, otherwise the REPL takes Tab
+        if let Some(TerminalEvent::Input(Event::Key(Key::Char('\t')))) = term_event_value {
+            self.completions.clear();
+
+            // a running pipeline reads it from stdin
+            if let CommandState::Pipeline(pipeline) = &self.state {
+                if pipeline.read().unwrap().accepts_input() {
+                    drop(cmd_obj);
+                    return pipeline.write().unwrap().send_cmd_obj(obj);
+                }
+            }
+
+            return match self.state.clone() {
+                CommandState::BuiltinArg(builtin, mut arg) if builtin.arg_type().ends_with("Path") => {
+                    self.complete_path(&mut arg);
+                    TreeNavResult::Continue
+                }
+                CommandState::DirStack(n) => {
+                    let mut entry = self.dirstack.get(n);
+                    self.complete_path(&mut entry);
+                    TreeNavResult::Continue
+                }
+                CommandState::Incubator(pipeline) |
+                CommandState::Pipeline(pipeline) if !pipeline.read().unwrap().accepts_input() => {
+                    self.complete_process(pipeline)
+                }
+                _ => TreeNavResult::Exit
+            };
+        }
+        self.completions.clear();
+
+        // end of input is only meaningful for a running pipeline
+        if let Some(TerminalEvent::Input(Event::Key(Key::Ctrl('d')))) = term_event_value {
+            if let CommandState::Pipeline(pipeline) = &self.state {
+                if pipeline.read().unwrap().accepts_input() {
+                    pipeline.write().unwrap().send_eof();
+                    return TreeNavResult::Continue;
+                }
+            }
+            return TreeNavResult::Exit;
+        }
+
+        // interrupt a running pipeline
+        if let Some(TerminalEvent::Input(Event::Key(Key::Ctrl('c')))) = term_event_value {
+            if let CommandState::Pipeline(pipeline) = &self.state {
+                let mut p = pipeline.write().unwrap();
+                if p.is_running() {
+                    p.cancel();
+                    return TreeNavResult::Continue;
+                }
+            }
+        }
+
+        // stop a running pipeline, it continues with `fg` or `bg`
+        if let Some(TerminalEvent::Input(Event::Key(Key::Ctrl('z')))) = term_event_value {
+            if let CommandState::Pipeline(pipeline) = &self.state {
+                let mut p = pipeline.write().unwrap();
+                if p.is_running() {
+                    p.suspend();
+                    return TreeNavResult::Exit;
+                }
+            }
+        }
+
+        // edit the directory stack, each press moves to the next entry
+        if let Some(TerminalEvent::Input(Event::Key(Key::Ctrl('s')))) = term_event_value {
+            match self.state.clone() {
+                CommandState::DirStack(n) => {
+                    self.leave_dirstack();
+                    if n + 1 < self.dirstack.len() {
+                        self.edit_dirstack(n + 1);
+                    }
+                }
+                CommandState::Incubator(_) if self.dirstack.len() > 0 => {
+                    self.edit_dirstack(0);
+                }
+                _ => {}
+            }
+            return TreeNavResult::Continue;
+        }
+
+        // rotate the directory stack, like `pushd +1`
+        if let Some(TerminalEvent::Input(Event::Key(Key::Ctrl('o')))) = term_event_value {
+            if let CommandState::DirStack(_) = self.state {
+                self.leave_dirstack();
+            }
+            if let Some(pushd) = crate::builtin::get("pushd") {
+                if crate::builtin::dir_stack().len() > 0 {
+                    let pipeline = self.pipeline();
+                    let mut p = pipeline.write().unwrap();
+                    p.pty_reset();
+                    p.run_builtin(&*pushd, &[ "+1".into() ]);
+                    drop(p);
+                    self.sync_dirs();
+                }
+            }
+            return TreeNavResult::Continue;
+        }
+
+        // copy as shell text
+        if let Some(TerminalEvent::Input(Event::Key(Key::Ctrl('y')))) = term_event_value {
+            self.pipeline().write().unwrap().copy_sh();
+            return TreeNavResult::Continue;
+        }
+
         match self.state.clone() {
             CommandState::Incubator(mut incubator_editor) => {
                 match char_value {
-                    Some(' ') => {
+                    Some(' ') if !incubator_editor.read().unwrap().in_quote() => {
                         let strings = incubator_editor.read().unwrap().get_strings();
 
-                        let mut cd_cmd = false;
-                        
-                        if strings.len() > 0 {
-                            if strings[0].len() > 0 {
-                                if strings[0][0] == "cd" {
-                                    cd_cmd = true;
-                                }
-                            }
-                        }
+                        // builtins with a structured argument get their own editor
+                        let builtin = strings.first()
+                            .and_then(|argv| argv.first())
+                            .and_then(|name| crate::builtin::get(name))
+                            .filter(|builtin| builtin.arg_type() != "ProcessArg");
 
-                        if cd_cmd {
-                            self.grid.insert(Point2::new(2,0), nested::terminal::make_label("cd "));
+                        if let Some(builtin) = builtin {
+                            self.grid.insert(Point2::new(3, 0), nested::terminal::make_label(&format!("{} ", builtin.name())));
                             incubator_editor.write().unwrap().editor.goto(TreeCursor::none());
 
                             let se = self.sum_editor.get_edit::<SumEditor>().unwrap();
                             let mut se = se.write().unwrap();
 
+                            let mut arg_node = Context::make_node(&self.ctx, Context::parse(&self.ctx, builtin.arg_type()), SingletonBuffer::new(2).get_port()).unwrap();
+                            if builtin.arg_type() == "Path" {
+                                let expect = crate::builtin::signature_type(builtin.name(), "$1")
+                                    .and_then(|ladder| crate::path::FileKind::from_type(&ladder));
+                                crate::path::highlight_segments(&mut arg_node, Some(self.cwd.clone()), expect);
+                            }
+                            se.editors[1] = arg_node.clone();
                             se.select(1);
-                            let mut path_node = se.editors[1].clone();
 
-                            self.state = CommandState::CD(path_node.clone());
+                            self.state = CommandState::BuiltinArg(builtin, arg_node.clone());
 
-                            path_node.goto(TreeCursor::home());
+                            arg_node.goto(TreeCursor::home());
                         } else {
                             incubator_editor.write().unwrap().send_cmd_obj(obj);
                             self.state = CommandState::Pipeline(incubator_editor);
@@ -249,10 +660,10 @@ impl ObjCommander for Command {
                 }
             }
 
-            CommandState::CD(mut path) => {
+            CommandState::BuiltinArg(builtin, arg) => {
                 match term_event_value {
                     Some(TerminalEvent::Input(Event::Key(Key::Backspace))) => {
-                        if path.get_cursor().tree_addr.iter().fold(
+                        if arg.get_cursor().tree_addr.iter().fold(
                             true,
                             |s, x| s && *x == 0
                         ) {
@@ -265,7 +676,7 @@ impl ObjCommander for Command {
                             self.state = CommandState::Incubator(ed);
                             se.select(0);
 
-                            self.grid.remove(Point2::new(2, 0));
+                            self.grid.remove(Point2::new(3, 0));
 
                             se.goto(TreeCursor {
                                 leaf_mode: ListCursorMode::Insert,
@@ -280,68 +691,31 @@ impl ObjCommander for Command {
                     _ => {
                         match char_value {
                             Some('\n') => {
-                                // todo set cwd
-                                /*
-                                let pwd_edit = self.ctx.write().unwrap()
-                                    .get_obj("PWD")
-                                    .get_edit::<ListEditor>().unwrap();
-                                */
-                                let se = self.sum_editor.get_edit::<SumEditor>().unwrap();
-                                let mut se = se.write().unwrap();
-
-                                let cwd_edit = self.cwd_node.get_edit::<ListEditor>().unwrap();
-                                let mut cwd_edit = cwd_edit.write().unwrap();
-
-                                let cd_path = path.get_data_view::<dyn SequenceView<Item = NestedNode>>(
-                                    vec![
-                                        "<List PathSegment>"
-                                    ].into_iter()
-                                ).unwrap();
-
-                                let mut cd_path_str = String::new();
-
-                                for segment in cd_path.iter() {
-                                    let mut node = segment.clone();
-                                    node.goto(TreeCursor::none());
-
-                                    let node_view = node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Char>"].into_iter());
-
-                                    let mut arg = String::new();
-                                    for k in 0..node_view.len().unwrap_or(0) {
-                                        let char_node = node_view.get(&k).unwrap();
-                                        let char_view = char_node.get_data_view::<dyn SingletonView<Item = Option<char>>>(vec![].into_iter());
-                                        if let Some(c) = char_view.get() {
-                                            arg.push(c);
-
-                                            cd_path_str.push(c)
-                                        }
-                                    }
-
-                                    if arg.len() > 0 {
-                                        if arg == ".." {
-                                            let len = cwd_edit.data.len();
-                                            cwd_edit.data.remove(len-1);
-                                        } else {
-                                            cwd_edit.data.push(Arc::new(RwLock::new(node)));
-                                        }
-                                    }
+                                let mut arg_str = crate::path::path_string(&arg);
+                                if arg_str.len() > 1 && arg_str.ends_with('/') {
+                                    arg_str.pop();
+                                }
+                                let args = if arg_str.len() > 0 { vec![ arg_str ] } else { vec![] };
 
-                                    cd_path_str.push('/');
+                                let pipeline_editor = self.pipeline();
+                                {
+                                    let mut p = pipeline_editor.write().unwrap();
+                                    p.pty_reset();
+                                    p.run_builtin(&*builtin, &args);
                                 }
+                                self.sync_dirs();
+
+                                let se = self.sum_editor.get_edit::<SumEditor>().unwrap();
+                                let mut se = se.write().unwrap();
 
-                                std::env::set_current_dir(std::path::Path::new(&cd_path_str));
-                                
                                 se.editors[1] = Context::make_node(&self.ctx, Context::parse(&self.ctx, "Path"), SingletonBuffer::new(2).get_port()).unwrap();
 
-                                let pipeline_editor = se.editors[0].get_edit::<PipelineLauncher>().unwrap();
-                                pipeline_editor.write().unwrap().cwd = Some(self.get_cwd_string());
-                                pipeline_editor.write().unwrap().pty_reset();
-                                let pipeline_list_edit = pipeline_editor.write().unwrap().editor.get_edit::<ListEditor>().unwrap();
+                                let pipeline_list_edit = pipeline_editor.read().unwrap().editor.get_edit::<ListEditor>().unwrap();
                                 pipeline_list_edit.write().unwrap().data.clear();
 
                                 self.state = CommandState::Incubator(pipeline_editor);
 
-                                self.grid.remove(Point2::new(2, 0));
+                                self.grid.remove(Point2::new(3, 0));
                                 se.select(0);
                                 se.goto(TreeCursor::home());
 
@@ -355,11 +729,41 @@ impl ObjCommander for Command {
                 }
             }
 
+            CommandState::DirStack(_) => {
+                match char_value {
+                    Some('\n') => {
+                        self.leave_dirstack();
+                        TreeNavResult::Continue
+                    }
+                    _ => {
+                        self.sum_editor.send_cmd_obj(obj)
+                    }
+                }
+            }
+
             CommandState::Pipeline(mut pipeline) => {
+                if pipeline.read().unwrap().accepts_input() {
+                    // forward keystrokes to stdin of the running pipeline
+                    drop(cmd_obj);
+                    return pipeline.write().unwrap().send_cmd_obj(obj);
+                }
+
                 match char_value {
                     Some('\n') => {
-                        pipeline.write().unwrap().launch();
-                        TreeNavResult::Exit
+                        let accepts_input = {
+                            let mut p = pipeline.write().unwrap();
+                            p.launch();
+                            p.accepts_input() || p.is_captured()
+                        };
+
+                        // `cd` and friends may have moved
+                        self.sync_dirs();
+
+                        if accepts_input {
+                            TreeNavResult::Continue
+                        } else {
+                            TreeNavResult::Exit
+                        }
                     },
                     _ => {
                         let mut p = pipeline.write().unwrap();
@@ -382,3 +786,30 @@ impl ObjCommander for Command {
     }
 }
 
+impl ObjCommander for Command {
+    fn send_cmd_obj(&mut self, obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+        let result = self.dispatch(obj);
+        self.register_job();
+        result
+    }
+}
+
+impl crate::job::JobInput for Command {
+    fn captures_input(&self) -> bool {
+        match &self.state {
+            CommandState::Incubator(pipeline) => pipeline.read().unwrap().is_captured(),
+            CommandState::Pipeline(pipeline) => {
+                let p = pipeline.read().unwrap();
+                p.is_captured() || p.accepts_input()
+            }
+            _ => false
+        }
+    }
+
+    fn send_input(&mut self, event: &TerminalEvent) -> TreeNavResult {
+        self.send_cmd_obj(ReprTree::new_leaf(
+            Context::parse(&self.ctx, "TerminalEvent"),
+            AnyOuterViewPort::from(SingletonBuffer::new(event.clone()).get_port())
+        ))
+    }
+}
@@ -0,0 +1,157 @@
+use {
+    std::{
+        collections::{BTreeSet, HashMap},
+        sync::{Arc, RwLock}
+    },
+    laddertypes::{TypeTerm},
+    nested::{
+        type_system::{Context}
+    }
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    /// what gets inserted
+    pub text: String,
+
+    /// pattern of an operand which has to follow `text`, e.g. `[0-9]*`
+    pub slot: Option<String>,
+
+    /// stdout type of the command with this candidate
+    pub hint: Option<String>,
+
+    pub color: (u8, u8, u8),
+}
+
+impl Candidate {
+    fn new(text: String, color: (u8, u8, u8)) -> Self {
+        Candidate { text, slot: None, hint: None, color }
+    }
+
+    /// text shown in the list of candidates
+    pub fn label(&self) -> String {
+        let mut label = self.text.clone();
+        if let Some(slot) = self.slot.as_ref() {
+            label.push_str(slot);
+        }
+        if let Some(hint) = self.hint.as_ref() {
+            label.push_str(" \u{2192} ");
+            label.push_str(hint);
+        }
+        label
+    }
+}
+
+/// longest prefix which all candidates share
+pub fn common_prefix(candidates: &[Candidate]) -> String {
+    crate::path::common_prefix(candidates.iter().map(|c| c.text.as_str()))
+}
+
+fn parse_ladder(ctx: &Arc<RwLock<Context>>, ladder: &str) -> Option<TypeTerm> {
+    ctx.read().unwrap().type_term_from_str(ladder).ok()
+}
+
+/// names of executables on `$PATH`, builtins and aliases which start with `prefix`.
+/// after an `upstream` stage (its argv), commands whose stdin accepts its stdout come first,
+/// the closer the match the better.
+pub fn command_names(
+    ctx: &Arc<RwLock<Context>>,
+    prefix: &str,
+    upstream: Option<&[String]>
+) -> Vec<Candidate> {
+    let mut names = BTreeSet::new();
+    names.extend(crate::which::path_names().into_iter().filter(|name| name.starts_with(prefix)));
+    names.extend(crate::builtin::names().into_iter().map(String::from).filter(|name| name.starts_with(prefix)));
+    names.extend(crate::which::aliases().into_iter().map(|(name, _)| name).filter(|name| name.starts_with(prefix)));
+
+    let upstream_type = upstream
+        .and_then(|cmd| crate::typedb::lookup(&cmd.join(" "), "<1"))
+        .and_then(|ladder| parse_ladder(ctx, &ladder));
+
+    // stdin types which the typedb knows per command name
+    let mut stdin_types = HashMap::<String, Vec<String>>::new();
+    if upstream_type.is_some() {
+        for entry in crate::typedb::entries().iter() {
+            if let Some(stdin) = entry.get(">0") {
+                for name in entry.command_names() {
+                    if names.contains(&name) {
+                        stdin_types.entry(name).or_insert_with(Vec::new).push(stdin.into());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut ranked = Vec::new();
+    let mut rest = Vec::new();
+
+    for name in names {
+        let rank = match (&upstream_type, stdin_types.get(&name)) {
+            (Some(stdout), Some(ladders)) => Some(
+                ladders.iter()
+                    .filter_map(|ladder| parse_ladder(ctx, ladder))
+                    .filter_map(|stdin| stdout.is_syntactic_subtype_of(&stdin).ok())
+                    .min()
+            ),
+            _ => None
+        };
+
+        match rank {
+            // compatible
+            Some(Some(depth)) => ranked.push((depth, Candidate::new(name, (80, 200, 80)))),
+            // known, but can't consume the upstream output
+            Some(None) => rest.push(Candidate::new(name, (110, 110, 110))),
+            None => rest.push(Candidate::new(name, (200, 200, 200)))
+        }
+    }
+
+    ranked.sort_by_key(|(depth, _)| *depth);
+    ranked.into_iter().map(|(_, candidate)| candidate).chain(rest).collect()
+}
+
+/// options and operands which the typedb entries accept after `args`
+/// for the argument starting with `prefix`
+pub fn arguments(args: &[String], prefix: &str) -> Vec<Candidate> {
+    let typed = format!("{} {}", args.join(" "), prefix);
+    let name = match args.first() {
+        Some(name) => name,
+        None => { return Vec::new(); }
+    };
+
+    let mut seen = BTreeSet::new();
+    let mut candidates = Vec::new();
+
+    for entry in crate::typedb::entries().iter() {
+        if !entry.command_names().contains(name) {
+            continue;
+        }
+
+        for continuation in entry.continuations(&typed) {
+            let (word, slot) = match continuation {
+                crate::typedb::Continuation::Word(word) => (word, None),
+                crate::typedb::Continuation::Slot(word, slot) => (word, Some(slot))
+            };
+
+            let text = format!("{}{}", prefix, word);
+
+            // quotes are left to the `ProcessArg` editor
+            if text.starts_with('"') || text.starts_with('\'') {
+                continue;
+            }
+            if (text.len() == 0 && slot.is_none()) || !seen.insert((text.clone(), slot.clone())) {
+                continue;
+            }
+
+            candidates.push(Candidate {
+                color: if slot.is_some() { (110, 110, 110) } else { (200, 200, 200) },
+                hint: entry.get("<1").map(String::from),
+                text,
+                slot
+            });
+        }
+    }
+
+    candidates
+}
@@ -0,0 +1,295 @@
+use {
+    std::{
+        collections::BTreeMap,
+        sync::{Arc, RwLock},
+        sync::atomic::{AtomicU64, Ordering}
+    },
+    r3vi::{
+        view::{OuterViewPort, singleton::*, sequence::*},
+        buffer::{singleton::*, index_hashmap::*}
+    },
+    laddertypes::{TypeTerm},
+    nested::{
+        type_system::{Context, MorphismTypePattern, ReprTree},
+        editors::list::*,
+        terminal::{make_label, TerminalAtom, TerminalEvent, TerminalStyle, TerminalView, TerminalProjections},
+        tree::{NestedNode, TreeNav, TreeCursor},
+        commander::ObjCommander
+    },
+    cgmath::Point2,
+    termion::event::{Event, Key}
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// variables of the shell, initialized from the environment of the process
+static VARS: RwLock<Option<BTreeMap<String, String>>> = RwLock::new(None);
+
+/// incremented on every change, so that views can catch up
+static REVISION: AtomicU64 = AtomicU64::new(0);
+
+fn with_vars<T>(f: impl FnOnce(&mut BTreeMap<String, String>) -> T) -> T {
+    let mut vars = VARS.write().unwrap();
+    f(vars.get_or_insert_with(|| std::env::vars().collect()))
+}
+
+pub fn get(name: &str) -> Option<String> {
+    with_vars(|vars| vars.get(name).cloned())
+}
+
+/// the process environment is left alone,
+/// children get their environment from `vars()`
+pub fn set(name: &str, value: &str) {
+    with_vars(|vars| vars.insert(name.into(), value.into()));
+    REVISION.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn unset(name: &str) -> Option<String> {
+    let old = with_vars(|vars| vars.remove(name));
+    REVISION.fetch_add(1, Ordering::Relaxed);
+    old
+}
+
+/// all variables, sorted by name
+pub fn vars() -> Vec<(String, String)> {
+    with_vars(|vars| vars.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+}
+
+/// replaces all variables
+pub fn replace(new_vars: Vec<(String, String)>) {
+    for (name, _) in vars() {
+        if !new_vars.iter().any(|(n, _)| *n == name) {
+            unset(&name);
+        }
+    }
+    for (name, value) in new_vars {
+        if get(&name).as_ref() != Some(&value) {
+            set(&name, &value);
+        }
+    }
+}
+
+pub fn revision() -> u64 {
+    REVISION.load(Ordering::Relaxed)
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// style of the `x`-th char in `NAME=value`, with `=` at `eq`
+pub fn style_assignment(eq: usize, x: usize, a: &TerminalAtom) -> TerminalAtom {
+    a.add_style_back(TerminalStyle::fg_color(
+        if x < eq {
+            (80, 180, 200)
+        } else if x == eq {
+            (120, 120, 120)
+        } else {
+            (200, 200, 120)
+        }
+    ))
+}
+
+fn char_of(node: &NestedNode) -> Option<char> {
+    node.get_data_view::<dyn SingletonView<Item = Option<char>>>(vec![].into_iter()).get()
+}
+
+fn assignment_string(node: &NestedNode) -> String {
+    let chars = node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Char>"].into_iter());
+    (0..chars.len().unwrap_or(0))
+        .filter_map(|i| chars.get(&i))
+        .filter_map(|c| char_of(&c))
+        .collect()
+}
+
+pub fn init_ctx(ctx: &mut Context) {
+    ctx.add_list_typename("EnvAssignment".into());
+    ctx.add_morphism(
+        // <List Char>  =>  EnvAssignment
+        MorphismTypePattern {
+            src_tyid: ctx.get_typeid("List"),
+            dst_tyid: ctx.get_typeid("EnvAssignment").unwrap()
+        },
+        Arc::new(
+            |mut node, _dst_type:_| {
+                PTYListController::for_node( &mut node, None, None );
+                PTYListStyle::for_node( &mut node, ("","","") );
+
+                if let Some(list) = node.get_edit::<ListEditor>() {
+                    let seg_view = PTYListStyle::new( ("", "", "") ).get_seg_seq_view( &mut list.write().unwrap() );
+
+                    node.view = Some(
+                        seg_view
+                            .enumerate()
+                            .map(move |(n, segment)| {
+                                let n = n.clone();
+                                let list = list.clone();
+                                segment.map_item(move |_, a| {
+                                    let list = list.read().unwrap();
+                                    let eq = (0..list.data.len())
+                                        .position(|i| char_of(&list.data.get(i)) == Some('='))
+                                        .unwrap_or(usize::MAX);
+                                    style_assignment(eq, n, a)
+                                })
+                            })
+                            .to_grid_horizontal()
+                            .flatten()
+                    );
+                }
+
+                Some(node)
+            }
+        )
+    );
+
+    ctx.add_node_ctor(
+        "EnvAssignment", Arc::new(
+            |ctx: Arc<RwLock<Context>>, dst_typ: TypeTerm, depth| {
+                let mut node = Context::make_node( &ctx, Context::parse(&ctx, "<List Char>"), depth ).unwrap();
+                node = node.morph(dst_typ);
+                Some(node)
+            }
+        )
+    );
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// lists the variables of the shell,
+/// while focused they can be edited and are applied when leaving
+pub struct EnvPanel {
+    ctx: Arc<RwLock<Context>>,
+    node: NestedNode,
+    revision: u64,
+    focused: bool,
+    grid: IndexBuffer<Point2<i16>, OuterViewPort<dyn TerminalView>>,
+}
+
+impl EnvPanel {
+    pub fn new(ctx: Arc<RwLock<Context>>) -> Self {
+        let mut node = Context::make_node(&ctx, Context::parse(&ctx, "<List EnvAssignment>"), SingletonBuffer::new(1).get_port()).unwrap();
+        PTYListController::for_node( &mut node, Some('\n'), None );
+
+        let list = node.get_edit::<ListEditor>().unwrap();
+        let entries = PTYListStyle::new( ("", "", "") ).get_seg_seq_view( &mut list.write().unwrap() );
+
+        let mut grid = IndexBuffer::new();
+        grid.insert_iter(vec![
+            (Point2::new(0, 0), Self::header(false)),
+            (Point2::new(0, 1), entries.to_grid_vertical().flatten())
+        ]);
+
+        let mut panel = EnvPanel {
+            ctx,
+            node,
+            revision: 0,
+            focused: false,
+            grid
+        };
+        panel.load();
+        panel
+    }
+
+    fn header(focused: bool) -> OuterViewPort<dyn TerminalView> {
+        if focused {
+            make_label("env (Esc to apply)").with_style(TerminalStyle::bold(true))
+        } else {
+            make_label("env (F2 to edit)").with_fg_color((90, 90, 90))
+        }
+    }
+
+    pub fn view(&self) -> OuterViewPort<dyn TerminalView> {
+        self.grid.get_port().flatten()
+    }
+
+    /// fills the list with the current variables
+    fn load(&mut self) {
+        let list = self.node.get_edit::<ListEditor>().unwrap();
+        list.write().unwrap().data.clear();
+
+        for (name, value) in vars() {
+            let mut entry = Context::make_node(&self.ctx, Context::parse(&self.ctx, "EnvAssignment"), SingletonBuffer::new(2).get_port()).unwrap();
+            entry.goto(TreeCursor::home());
+            for c in format!("{}={}", name, value).chars() {
+                entry.send_cmd_obj(ReprTree::from_char(&self.ctx, c));
+            }
+            entry.goto(TreeCursor::none());
+
+            list.write().unwrap().data.push(entry);
+        }
+
+        self.revision = revision();
+    }
+
+    /// shows changes made by `export` & co.
+    pub fn refresh(&mut self) {
+        if !self.is_focused() && self.revision != revision() {
+            self.load();
+        }
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// starts or ends editing, ending applies all entries.
+    /// an entry which is no assignment keeps the panel open
+    pub fn toggle(&mut self) {
+        if self.is_focused() {
+            match self.apply() {
+                Ok(()) => {
+                    self.grid.remove(Point2::new(0, 2));
+                    self.node.goto(TreeCursor::none());
+                    self.focused = false;
+                }
+                Err(msg) => {
+                    self.grid.insert(Point2::new(0, 2), make_label(&msg).with_fg_color((200, 80, 80)));
+                }
+            }
+        } else {
+            self.focused = true;
+            self.node.goto(TreeCursor::home());
+        }
+        self.grid.insert(Point2::new(0, 0), Self::header(self.is_focused()));
+    }
+
+    /// sets the variables to the entries, emptied entries are removed.
+    /// nothing is changed if an entry lacks its `NAME=`
+    fn apply(&mut self) -> Result<(), String> {
+        let entries = self.node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List EnvAssignment>"].into_iter());
+
+        let mut new_vars = Vec::new();
+        for (i, entry) in (0..entries.len().unwrap_or(0)).filter_map(|i| entries.get(&i).map(|e| (i, e))) {
+            let s = assignment_string(&entry);
+            if s.len() == 0 {
+                continue;
+            }
+            match crate::parse::split_assignment(&s) {
+                Some((name, value)) => new_vars.push((name.to_string(), value.to_string())),
+                None => { return Err(format!("line {}: `{}` is no NAME=value", i + 1, s)); }
+            }
+        }
+
+        replace(new_vars);
+        self.load();
+        Ok(())
+    }
+
+    pub fn handle_terminal_event(&mut self, event: &TerminalEvent) {
+        match event {
+            TerminalEvent::Input(Event::Key(Key::Esc)) => { self.toggle(); }
+            TerminalEvent::Input(Event::Key(Key::Left)) => { self.node.pxev(); }
+            TerminalEvent::Input(Event::Key(Key::Right)) => { self.node.nexd(); }
+            TerminalEvent::Input(Event::Key(Key::Up)) => { self.node.up(); }
+            TerminalEvent::Input(Event::Key(Key::Down)) => { self.node.dn(); }
+            TerminalEvent::Input(Event::Key(Key::Backspace)) => {
+                self.node.send_cmd_obj(ListCmd::DeletePxev.into_repr_tree(&self.ctx));
+            }
+            TerminalEvent::Input(Event::Key(Key::Delete)) => {
+                self.node.send_cmd_obj(ListCmd::DeleteNexd.into_repr_tree(&self.ctx));
+            }
+            TerminalEvent::Input(Event::Key(Key::Char(c))) => {
+                self.node.send_cmd_obj(ReprTree::from_char(&self.ctx, *c));
+            }
+            _ => {}
+        }
+    }
+}
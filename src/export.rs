@@ -0,0 +1,151 @@
+use {
+    std::io::Write,
+    crate::parse::{Word, WordPart, Process, Pipeline}
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+fn is_safe_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_./:,+=@%^".contains(c)
+}
+
+/// quotes a single argument for POSIX sh,
+/// using as little quoting as possible
+pub fn quote(arg: &str) -> String {
+    if arg.len() == 0 {
+        return "''".into();
+    }
+
+    if arg.chars().all(is_safe_char) {
+        return arg.into();
+    }
+
+    if !arg.contains('\'') {
+        format!("'{}'", arg)
+    } else if !arg.chars().any(|c| "\"$`\\!".contains(c)) {
+        format!("\"{}\"", arg)
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// quotes a word for POSIX sh, substitutions are kept as `"${NAME}"`
+pub fn word_to_sh(word: &Word) -> String {
+    fn push_double_quoted(s: &mut String, parts: &Vec<WordPart>) {
+        for part in parts.iter() {
+            match part {
+                WordPart::Literal(l) |
+                WordPart::SingleQuoted(l) => {
+                    for c in l.chars() {
+                        if "\"$`\\".contains(c) {
+                            s.push('\\');
+                        }
+                        s.push(c);
+                    }
+                }
+                WordPart::DoubleQuoted(inner) => push_double_quoted(s, inner),
+                // special parameters have no braced form in the parser
+                WordPart::Variable(name) if crate::parse::special_parameter(name).is_some() => { s.push('$'); s.push_str(name); }
+                WordPart::Variable(name) => { s.push_str("${"); s.push_str(name); s.push('}'); }
+            }
+        }
+    }
+
+    let literal = word.parts.iter().all(|part| match part {
+        WordPart::Literal(_) | WordPart::SingleQuoted(_) => true,
+        _ => false
+    });
+
+    if literal {
+        quote(&word.to_arg())
+    } else {
+        let mut s = String::from("\"");
+        push_double_quoted(&mut s, &word.parts);
+        s.push('"');
+        s
+    }
+}
+
+pub fn process_to_sh(process: &Process) -> String {
+    process.assignments.iter()
+        .map(|(name, value)| format!("{}={}", name, word_to_sh(value)))
+        .chain(process.words.iter().map(word_to_sh))
+        .chain(process.redirects.iter().map(|r| r.to_str().into()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn pipeline_to_sh(pipeline: &Pipeline) -> String {
+    let mut sh = pipeline.processes.iter()
+        .map(process_to_sh)
+        .collect::<Vec<_>>()
+        .join(" | ");
+
+    if pipeline.background {
+        sh.push_str(" &");
+    }
+    sh
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0)
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | (b[2] as u32);
+
+        out.push(TABLE[(n >> 18) as usize & 63] as char);
+        out.push(TABLE[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { TABLE[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { TABLE[n as usize & 63] as char } else { '=' });
+    }
+    out
+}
+
+/// places text in the clipboard of the hosting terminal (OSC 52)
+pub fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("abc-1/x.y"), "abc-1/x.y");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), "\"it's\"");
+        assert_eq!(quote("it's $x"), "'it'\\''s $x'");
+    }
+
+    #[test]
+    fn words() {
+        let word = Word { pos: 0, parts: vec![ WordPart::Literal("a".into()), WordPart::Variable("X".into()) ] };
+        assert_eq!(word_to_sh(&word), "\"a${X}\"");
+
+        let word = Word { pos: 0, parts: vec![ WordPart::Variable("$".into()), WordPart::Literal("x".into()) ] };
+        assert_eq!(word_to_sh(&word), "\"$$x\"");
+
+        let word = Word { pos: 0, parts: vec![ WordPart::Literal("\"q\"".into()), WordPart::Variable("X".into()) ] };
+        assert_eq!(word_to_sh(&word), "\"\\\"q\\\"${X}\"");
+    }
+
+    #[test]
+    fn pipelines() {
+        let pipeline = crate::parse::parse_pipeline("FOO='a b' env 2>&1 | sort &").unwrap();
+        assert_eq!(pipeline_to_sh(&pipeline), "FOO='a b' env 2>&1 | sort &");
+    }
+}
@@ -10,7 +10,7 @@ use {
         type_system::{Context, ReprTree},
         editors::{list::{ListCursorMode, ListEditor, PTYListController, PTYListStyle, ListCmd}, typeterm::TypeTermEditor},
         terminal::{make_label, Terminal, TerminalCompositor, TerminalEditor, TerminalEvent, TerminalStyle, TerminalProjections},
-        tree::{TreeNav, TreeCursor},
+        tree::{NestedNode, TreeNav, TreeCursor, TreeNavResult},
         commander::ObjCommander
     },
     /* refactoring proposal
@@ -18,16 +18,63 @@ use {
      *  nested-terminal::{event_loop, display_loop}
      */
     std::sync::{Arc, RwLock},
+    std::io::Write,
     termion::event::{Event, Key},
 };
 
-pub async fn tui_repl(ctx: Arc<RwLock<Context>>) {
+/// bracketed paste mode: the terminal wraps pasted text in these
+const PASTE_ON: &str = "\x1b[?2004h";
+const PASTE_OFF: &str = "\x1b[?2004l";
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// parses pasted text into new commands behind the one at the cursor.
+/// a pipeline with a syntax error shows it as diagnostic.
+fn insert_pasted(ctx: &Arc<RwLock<Context>>, node: &mut NestedNode, text: &str) {
+    let list = match node.get_edit::<ListEditor>() {
+        Some(list) => list,
+        None => { return; }
+    };
+
+    let len = list.read().unwrap().data.len();
+    let mut idx = match node.get_cursor().tree_addr.first() {
+        Some(n) if *n >= 0 => (*n as usize + 1).min(len),
+        _ => len
+    };
+
+    for (_line, src) in crate::parse::split_script(text) {
+        let mut command = crate::command::Command::new(
+            ctx.clone(),
+            std::env::current_dir().unwrap()
+                .into_os_string().into_string().unwrap()
+        );
+        let _ = command.load_str(&src);
+
+        list.write().unwrap().data.insert(idx, command.into_node());
+        idx += 1;
+    }
+}
+
+/// position of the command whose commander is `input`
+fn command_index(node: &NestedNode, input: &Arc<RwLock<dyn crate::job::JobInput>>) -> Option<usize> {
+    let list = node.get_edit::<ListEditor>()?;
+    let list = list.read().unwrap();
+    (0..list.data.len()).find(|i| {
+        list.data.get(*i).cmd.get()
+            .map(|cmd| Arc::as_ptr(&cmd) as *const () == Arc::as_ptr(input) as *const ())
+            .unwrap_or(false)
+    })
+}
+
+pub async fn tui_repl(ctx: Arc<RwLock<Context>>, history: Vec<String>) {
     let term_port = ViewPort::new();
     let compositor = TerminalCompositor::new(term_port.inner());
 
     let mut term = Terminal::new(term_port.outer());
     let term_writer = term.get_writer();
 
+    let _ = write!(std::io::stdout(), "{}", PASTE_ON).and_then(|_| std::io::stdout().flush());
+
     let portmutex = Arc::new(RwLock::new(()));
 
     // Update Loop //
@@ -52,7 +99,24 @@ pub async fn tui_repl(ctx: Arc<RwLock<Context>>) {
             ctx.clone(),
             Context::parse(&ctx, "Command")
         );
+
+    // imported history //
+    for src in history.iter() {
+        crate::builtin::add_history(src.clone());
+
+        let mut command = crate::command::Command::new(
+            ctx.clone(),
+            std::env::current_dir().unwrap()
+                .into_os_string().into_string().unwrap()
+        );
+
+        if command.load_str(src).is_ok() {
+            process_list_editor.data.push(command.into_node());
+        }
+    }
+
     let ple_seg_view = PTYListStyle::new( ("", "", "") ).get_seg_seq_view( &mut process_list_editor );
+    let segments = ple_seg_view.clone();
 
     let cursor_widget = process_list_editor.get_cursor_widget();
 
@@ -73,6 +137,51 @@ pub async fn tui_repl(ctx: Arc<RwLock<Context>>) {
 
         let mut cur_size = SingletonBuffer::new(Vector2::new(10, 10));
 
+        let mut env_panel = crate::env::EnvPanel::new(ctx.clone());
+
+        let diag_view = node.get_diag().map(
+            |entry| {
+                let mut b = VecBuffer::new();
+                b.push(
+                     make_label("@").with_style(
+                         TerminalStyle::bold(true)
+                             .add(TerminalStyle::fg_color((120,120,0))))
+                );
+
+                for x in entry.addr.iter() {
+                    b.push(
+                        make_label(&format!("{}", x)).with_fg_color((0, 100, 20))
+                    );
+                    b.push(
+                        make_label(".")
+                            .map_item(|_p,a| a
+                               .add_style_back(TerminalStyle::bold(true))
+                               .add_style_back(TerminalStyle::fg_color((120,120,0))))
+                    );
+                }
+
+                b.push(entry.port.clone());
+                b.get_port()
+                    .to_sequence()
+                    .to_grid_horizontal()
+                    .flatten()
+                    .map_item(move |_p,a| {
+                        let select = false;
+                        if select {
+                            a.add_style_back(TerminalStyle::fg_color((60,60,60)))
+                        } else {
+                            *a
+                        }
+                    })
+            }
+        ).to_grid_vertical().flatten();
+
+        crate::layout::init_command_list(
+            vec![ cursor_widget.clone(), magic.clone(), make_label(" ") ],
+            segments,
+            vec![ make_label(" "), magic.clone(), diag_view.clone(), make_label(" "), env_panel.view() ]
+        );
+
         table.insert_iter(vec![
             (Point2::new(0, 1), cursor_widget),
             (Point2::new(0, 2), magic.clone()),
@@ -104,50 +213,19 @@ pub async fn tui_repl(ctx: Arc<RwLock<Context>>) {
 
             (Point2::new(0, 5), make_label(" ")),
             (Point2::new(0, 6), magic.clone()),
-            (Point2::new(0, 7), node.get_diag().map(
-                |entry| {
-                    let mut b = VecBuffer::new();
-                    b.push(
-                         make_label("@").with_style(
-                             TerminalStyle::bold(true)
-                                 .add(TerminalStyle::fg_color((120,120,0))))
-                    );
+            (Point2::new(0, 7), diag_view),
 
-                    for x in entry.addr.iter() {
-                        b.push(
-                            make_label(&format!("{}", x)).with_fg_color((0, 100, 20))
-                        );
-                        b.push(
-                            make_label(".")
-                                .map_item(|_p,a| a
-                                   .add_style_back(TerminalStyle::bold(true))
-                                   .add_style_back(TerminalStyle::fg_color((120,120,0))))
-                        );
-                    }
-
-                    b.push(entry.port.clone());
-                    b.get_port()
-                        .to_sequence()
-                        .to_grid_horizontal()
-                        .flatten()
-                        .map_item(move |_p,a| {
-                            let select = false;
-                            if select {
-                                a.add_style_back(TerminalStyle::fg_color((60,60,60)))
-                            } else {
-                                *a
-                            }
-                        })
-                }
-            ).to_grid_vertical().flatten())
+            (Point2::new(0, 8), make_label(" ")),
+            (Point2::new(0, 9), env_panel.view())
         ]);
         
-        let (_w, _h) = termion::terminal_size().unwrap();
+        let (w, h) = termion::terminal_size().unwrap();
+        crate::layout::set_terminal_size(Vector2::new(w as i16, h as i16));
 
         compositor
             .write()
             .unwrap()
-            .push(table.get_port().flatten());
+            .push(crate::layout::init_root(table.get_port().flatten()));
 
         node.goto(TreeCursor {
             leaf_mode: ListCursorMode::Insert,
@@ -155,6 +233,9 @@ pub async fn tui_repl(ctx: Arc<RwLock<Context>>) {
         });
 
     async_std::task::spawn(async move {
+        // text of a paste in progress
+        let mut paste : Option<String> = None;
+
         tp.update();
         loop {
             let ev = term.next_event().await;
@@ -162,9 +243,65 @@ pub async fn tui_repl(ctx: Arc<RwLock<Context>>) {
 
             if let TerminalEvent::Resize(new_size) = ev {
                 cur_size.set(new_size);
+                crate::layout::set_terminal_size(new_size);
+                crate::pty::resize_all(crate::layout::output_size());
+                crate::layout::resize_fullscreen();
                 term_port.inner().get_broadcast().notify(&IndexArea::Full);
                 continue;
             }
+
+            if let Some(n) = node.get_cursor().tree_addr.first() {
+                crate::layout::focus_command((*n).max(0) as usize);
+            }
+
+            // a program on the alternate screen gets all input
+            if crate::layout::fullscreen_input(&ev) {
+                tp.update();
+                continue;
+            }
+
+            // so does a foreground job, which need not be under the cursor after `fg`
+            if let Some(input) = crate::job::foreground_input() {
+                if let Some(n) = command_index(&node, &input) {
+                    crate::layout::focus_command(n);
+                }
+                input.write().unwrap().send_input(&ev);
+                tp.update();
+                continue;
+            }
+
+            // the environment panel takes all input while editing
+            if let TerminalEvent::Input(Event::Key(Key::F(2))) = ev {
+                env_panel.toggle();
+                tp.update();
+                continue;
+            }
+            if env_panel.is_focused() {
+                env_panel.handle_terminal_event(&ev);
+                tp.update();
+                continue;
+            }
+
+            // pasted text is parsed into commands instead of being typed
+            if let TerminalEvent::Input(Event::Unsupported(seq)) = &ev {
+                if seq.as_slice() == PASTE_START {
+                    paste = Some(String::new());
+                    continue;
+                }
+                if seq.as_slice() == PASTE_END {
+                    if let Some(text) = paste.take() {
+                        insert_pasted(&ctx, &mut node, &text);
+                    }
+                    tp.update();
+                    continue;
+                }
+            }
+            if let Some(text) = paste.as_mut() {
+                if let TerminalEvent::Input(Event::Key(Key::Char(c))) = ev {
+                    text.push(c);
+                }
+                continue;
+            }
 /*
             if let Some(process_editor) = process_list_editor.get_item() {
                 let mut pe = process_editor.write().unwrap();
@@ -215,7 +352,17 @@ pub async fn tui_repl(ctx: Arc<RwLock<Context>>) {
                 }
                 ListCursorMode::Insert => {
                     match ev {
-                        TerminalEvent::Input(Event::Key(Key::Ctrl('d'))) => break,
+                        TerminalEvent::Input(Event::Key(Key::Ctrl('d'))) => {
+                            // a running pipeline takes it as end of input
+                            if let TreeNavResult::Exit = node.send_cmd_obj(
+                                ReprTree::new_leaf(
+                                    Context::parse(&ctx, "TerminalEvent"),
+                                    AnyOuterViewPort::from(SingletonBuffer::new(ev.clone()).get_port())
+                                )
+                            ) {
+                                break;
+                            }
+                        }
 
                         // left hand
                         TerminalEvent::Input(Event::Key(Key::Ctrl('i'))) => { node.qpxev(); },
@@ -247,7 +394,17 @@ pub async fn tui_repl(ctx: Arc<RwLock<Context>>) {
                         TerminalEvent::Input(Event::Key(Key::Home)) => { node.qpxev(); }
                         TerminalEvent::Input(Event::Key(Key::End)) => { node.qnexd(); }
 
-                        TerminalEvent::Input(Event::Key(Key::Char('\t'))) => { node.toggle_leaf_mode(); }
+                        TerminalEvent::Input(Event::Key(Key::Char('\t'))) => {
+                            // the node may complete, otherwise toggle the mode
+                            if let TreeNavResult::Exit = node.send_cmd_obj(
+                                ReprTree::new_leaf(
+                                    Context::parse(&ctx, "TerminalEvent"),
+                                    AnyOuterViewPort::from(SingletonBuffer::new(ev.clone()).get_port())
+                                )
+                            ) {
+                                node.toggle_leaf_mode();
+                            }
+                        }
                         TerminalEvent::Input(Event::Key(Key::Backspace)) => {
                             node.send_cmd_obj(ListCmd::DeletePxev.into_repr_tree(&ctx));
                         },
@@ -268,9 +425,17 @@ pub async fn tui_repl(ctx: Arc<RwLock<Context>>) {
                 }
             }
 
+            if crate::builtin::exit_requested().is_some() {
+                break;
+            }
+
+            env_panel.refresh();
+            crate::substitution::refresh_previews();
             tp.update();
         }
 
+        let _ = write!(std::io::stdout(), "{}", PASTE_OFF).and_then(|_| std::io::stdout().flush());
+
         drop(term);
         drop(term_port);
     });
@@ -0,0 +1,192 @@
+use {
+    std::sync::{Arc, RwLock, Weak},
+    nested::{terminal::TerminalEvent, tree::TreeNavResult}
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JobState {
+    Running,
+    Stopped,
+    Done,
+}
+
+#[derive(Clone, Debug)]
+pub struct Job {
+    pub id: usize,
+    pub pgid: i32,
+    pub cmdline: String,
+    pub state: JobState,
+    pub foreground: bool,
+}
+
+impl std::fmt::Display for Job {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{}] {:<8} {}{}",
+               self.id,
+               format!("{:?}", self.state),
+               self.cmdline,
+               if self.foreground { "" } else { " &" })
+    }
+}
+
+/// all pipelines and PTY processes spawned by this shell
+static JOBS: RwLock<Vec<Job>> = RwLock::new(Vec::new());
+
+/// takes the keys of a job while it runs in the foreground
+pub trait JobInput: Send + Sync {
+    /// false while the job does not read keys, e.g. after end of input
+    fn captures_input(&self) -> bool;
+
+    fn send_input(&mut self, event: &TerminalEvent) -> TreeNavResult;
+}
+
+/// where the keys of each job go, the command which launched it
+static INPUTS: RwLock<Vec<(usize, Weak<RwLock<dyn JobInput>>)>> = RwLock::new(Vec::new());
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+pub fn add(pgid: i32, cmdline: String, foreground: bool) -> usize {
+    let mut jobs = JOBS.write().unwrap();
+    let id = jobs.iter().map(|j| j.id).max().unwrap_or(0) + 1;
+    jobs.push(Job {
+        id,
+        pgid,
+        cmdline,
+        state: JobState::Running,
+        foreground
+    });
+    id
+}
+
+pub fn get(id: usize) -> Option<Job> {
+    JOBS.read().unwrap().iter().find(|j| j.id == id).cloned()
+}
+
+pub fn set_state(id: usize, state: JobState) {
+    if let Some(job) = JOBS.write().unwrap().iter_mut().find(|j| j.id == id) {
+        job.state = state;
+        if state != JobState::Running {
+            job.foreground = false;
+        }
+    }
+}
+
+/// lists all jobs, finished jobs are dropped once they were listed
+pub fn list() -> Vec<Job> {
+    let mut jobs = JOBS.write().unwrap();
+    let list = jobs.clone();
+    jobs.retain(|j| j.state != JobState::Done);
+    list
+}
+
+/// resolves a job spec `%n` or `n`.
+/// without spec, the most recent stopped job, else the most recent job.
+pub fn resolve(spec: Option<&str>) -> Result<Job, String> {
+    let jobs = JOBS.read().unwrap();
+    let alive = || jobs.iter().rev().filter(|j| j.state != JobState::Done);
+
+    match spec {
+        None => alive().find(|j| j.state == JobState::Stopped)
+            .or_else(|| alive().next())
+            .cloned()
+            .ok_or("no current job".into()),
+        Some(spec) => {
+            let n = spec.trim_start_matches('%').parse::<usize>()
+                .map_err(|_| format!("{}: invalid job spec", spec))?;
+            alive().find(|j| j.id == n)
+                .cloned()
+                .ok_or(format!("{}: no such job", spec))
+        }
+    }
+}
+
+pub fn suspend(id: usize) {
+    if let Some(job) = get(id) {
+        unsafe { libc::kill(-job.pgid, libc::SIGTSTP); }
+    }
+}
+
+/// sends the keys to `input` while job `id` runs in the foreground
+pub fn set_input(id: usize, input: Weak<RwLock<dyn JobInput>>) {
+    let mut inputs = INPUTS.write().unwrap();
+    inputs.retain(|(i, input)| *i != id && input.strong_count() > 0);
+    inputs.push((id, input));
+}
+
+/// input of the most recent foreground job which reads keys.
+/// after `fg` this is not the command under the cursor.
+pub fn foreground_input() -> Option<Arc<RwLock<dyn JobInput>>> {
+    let inputs = INPUTS.read().unwrap().clone();
+    inputs.iter().rev()
+        .filter(|(id, _)| get(*id).map(|job| job.foreground && job.state == JobState::Running).unwrap_or(false))
+        .filter_map(|(_, input)| input.upgrade())
+        .find(|input| input.read().unwrap().captures_input())
+}
+
+/// continues a stopped job in the foreground or background
+pub fn resume(id: usize, foreground: bool) -> Result<Job, String> {
+    let mut jobs = JOBS.write().unwrap();
+    let job = jobs.iter_mut().find(|j| j.id == id).ok_or(format!("%{}: no such job", id))?;
+
+    if unsafe { libc::kill(-job.pgid, libc::SIGCONT) } != 0 {
+        return Err(format!("%{}: {}", id, std::io::Error::last_os_error()));
+    }
+
+    job.state = JobState::Running;
+    job.foreground = foreground;
+    Ok(job.clone())
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::CommandExt;
+    use termion::event::{Event, Key};
+
+    /// stands in for the command which launched a job
+    struct Keys(Vec<TerminalEvent>);
+
+    impl JobInput for Keys {
+        fn captures_input(&self) -> bool { true }
+
+        fn send_input(&mut self, event: &TerminalEvent) -> TreeNavResult {
+            self.0.push(event.clone());
+            TreeNavResult::Continue
+        }
+    }
+
+    #[test]
+    fn fg_sends_keys_to_the_job() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn().unwrap();
+
+        let id = add(child.id() as i32, "sleep 30".into(), true);
+        let keys = Arc::new(RwLock::new(Keys(Vec::new())));
+        let input : Arc<RwLock<dyn JobInput>> = keys.clone();
+        set_input(id, Arc::downgrade(&input));
+        assert!(foreground_input().is_some());
+
+        // Ctrl-Z
+        suspend(id);
+        set_state(id, JobState::Stopped);
+        assert!(foreground_input().is_none());
+
+        // `fg`, typed in another command
+        resume(resolve(None).unwrap().id, true).unwrap();
+        foreground_input().unwrap().write().unwrap()
+            .send_input(&TerminalEvent::Input(Event::Key(Key::Char('q'))));
+        assert!(matches!(
+            keys.read().unwrap().0.as_slice(),
+            [ TerminalEvent::Input(Event::Key(Key::Char('q'))) ]
+        ));
+
+        child.kill().ok();
+        child.wait().ok();
+    }
+}
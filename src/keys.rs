@@ -0,0 +1,378 @@
+use {
+    std::{
+        io::Read,
+        sync::Mutex,
+        sync::atomic::{AtomicBool, AtomicU32, Ordering}
+    },
+    termion::event::{Key, MouseButton, MouseEvent}
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// DEC private modes set by the program running inside a PTY
+#[derive(Default)]
+pub struct TermModes {
+    app_cursor: AtomicBool,
+
+    /// 0 (off), 9 (X10), 1000 (normal), 1002 (button-event) or 1003 (any-event)
+    mouse_tracking: AtomicU32,
+    sgr_mouse: AtomicBool,
+
+    alt_screen: AtomicBool,
+    alt_screen_hook: Mutex<Option<Box<dyn Fn(bool) + Send + Sync>>>,
+}
+
+impl TermModes {
+    /// `CSI ? <mode> h` / `CSI ? <mode> l`
+    pub fn set_private_mode(&self, mode: u32, on: bool) {
+        match mode {
+            1 => self.app_cursor.store(on, Ordering::Relaxed),
+            9 | 1000 | 1002 | 1003 => {
+                if on {
+                    self.mouse_tracking.store(mode, Ordering::Relaxed);
+                } else {
+                    self.mouse_tracking.compare_exchange(mode, 0, Ordering::Relaxed, Ordering::Relaxed).ok();
+                }
+            }
+            1006 => self.sgr_mouse.store(on, Ordering::Relaxed),
+            47 | 1047 | 1049 => {
+                if self.alt_screen.swap(on, Ordering::Relaxed) != on {
+                    if let Some(hook) = self.alt_screen_hook.lock().unwrap().as_ref() {
+                        hook(on);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn mouse_tracking(&self) -> u32 {
+        self.mouse_tracking.load(Ordering::Relaxed)
+    }
+
+    pub fn sgr_mouse(&self) -> bool {
+        self.sgr_mouse.load(Ordering::Relaxed)
+    }
+
+    pub fn alt_screen(&self) -> bool {
+        self.alt_screen.load(Ordering::Relaxed)
+    }
+
+    pub fn on_alt_screen(&self, f: impl Fn(bool) + Send + Sync + 'static) {
+        *self.alt_screen_hook.lock().unwrap() = Some(Box::new(f));
+    }
+
+    /// DECCKM: cursor keys send `ESC O x` instead of `ESC [ x`
+    pub fn app_cursor(&self) -> bool {
+        self.app_cursor.load(Ordering::Relaxed)
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+enum SniffState {
+    Ground,
+    Esc,
+    Csi { private: bool, params: String },
+}
+
+/// passes through the output of a PTY and
+/// picks up mode changes on the way
+pub struct ModeSniffer<R: Read> {
+    inner: R,
+    modes: std::sync::Arc<TermModes>,
+    state: SniffState,
+}
+
+impl<R: Read> ModeSniffer<R> {
+    pub fn new(inner: R, modes: std::sync::Arc<TermModes>) -> Self {
+        ModeSniffer {
+            inner,
+            modes,
+            state: SniffState::Ground
+        }
+    }
+
+    fn feed(&mut self, b: u8) {
+        self.state = match std::mem::replace(&mut self.state, SniffState::Ground) {
+            SniffState::Ground | SniffState::Esc if b == 0x1b => SniffState::Esc,
+            SniffState::Ground => SniffState::Ground,
+            SniffState::Esc if b == b'[' => SniffState::Csi { private: false, params: String::new() },
+            SniffState::Esc => SniffState::Ground,
+
+            SniffState::Csi { private: _, params } if b == b'?' && params.len() == 0 =>
+                SniffState::Csi { private: true, params },
+            SniffState::Csi { private, mut params } if (b.is_ascii_digit() || b == b';') && params.len() < 32 => {
+                params.push(b as char);
+                SniffState::Csi { private, params }
+            }
+            SniffState::Csi { private, params } => {
+                if private && (b == b'h' || b == b'l') {
+                    for mode in params.split(';').filter_map(|p| p.parse::<u32>().ok()) {
+                        self.modes.set_private_mode(mode, b == b'h');
+                    }
+                }
+                if b == 0x1b { SniffState::Esc } else { SniffState::Ground }
+            }
+        };
+    }
+}
+
+impl<R: Read> Read for ModeSniffer<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        for b in buf[..n].iter() {
+            self.feed(*b);
+        }
+        Ok(n)
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// encodes a key the way xterm sends it
+pub fn encode_key(key: &Key, app_cursor: bool) -> Option<Vec<u8>> {
+    let cursor = |c: u8| {
+        if app_cursor {
+            vec![ 0x1b, b'O', c ]
+        } else {
+            vec![ 0x1b, b'[', c ]
+        }
+    };
+    let tilde = |n: u8| format!("\x1b[{}~", n).into_bytes();
+
+    Some(match key {
+        Key::Char('\n') => vec![ b'\r' ],
+        Key::Char(c) => c.to_string().into_bytes(),
+        Key::Alt(c) => {
+            let mut bytes = vec![ 0x1b ];
+            bytes.extend(c.to_string().into_bytes());
+            bytes
+        }
+        Key::Ctrl(c) => vec![ ctrl_byte(*c)? ],
+        Key::Null => vec![ 0 ],
+        Key::Esc => vec![ 0x1b ],
+        Key::Backspace => vec![ 0x7f ],
+        Key::BackTab => b"\x1b[Z".to_vec(),
+
+        Key::Up => cursor(b'A'),
+        Key::Down => cursor(b'B'),
+        Key::Right => cursor(b'C'),
+        Key::Left => cursor(b'D'),
+        Key::Home => cursor(b'H'),
+        Key::End => cursor(b'F'),
+
+        Key::Insert => tilde(2),
+        Key::Delete => tilde(3),
+        Key::PageUp => tilde(5),
+        Key::PageDown => tilde(6),
+
+        Key::F(n @ 1..=4) => vec![ 0x1b, b'O', b'P' + n - 1 ],
+        Key::F(n @ 5..=12) => tilde(match n {
+            5 => 15,
+            6..=10 => n + 11,
+            _ => n + 12,
+        }),
+
+        _ => { return None; }
+    })
+}
+
+fn ctrl_byte(c: char) -> Option<u8> {
+    match c {
+        'a'..='z' => Some(c as u8 - b'a' + 1),
+        'A'..='Z' => Some(c as u8 - b'A' + 1),
+        '@' | ' ' | '2' => Some(0x00),
+        '[' | '3' => Some(0x1b),
+        '\\' | '4' => Some(0x1c),
+        ']' | '5' => Some(0x1d),
+        '^' | '6' => Some(0x1e),
+        '_' | '7' | '/' => Some(0x1f),
+        '?' | '8' => Some(0x7f),
+        _ => None
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// encodes a mouse event at the one-based local position (x, y)
+/// according to the reporting mode requested by the child.
+/// `pressed` remembers the held button for release and drag reports.
+pub fn encode_mouse(event: &MouseEvent, x: u16, y: u16, modes: &TermModes, pressed: &mut Option<u8>) -> Option<Vec<u8>> {
+    let tracking = modes.mouse_tracking();
+    if tracking == 0 {
+        return None;
+    }
+
+    let (button, release) = match event {
+        MouseEvent::Press(button, _, _) => {
+            let b = match button {
+                MouseButton::Left => 0,
+                MouseButton::Middle => 1,
+                MouseButton::Right => 2,
+                MouseButton::WheelUp => 64,
+                MouseButton::WheelDown => 65,
+            };
+            if b < 64 {
+                *pressed = Some(b);
+            }
+            (b, false)
+        }
+        MouseEvent::Release(_, _) => {
+            if tracking == 9 {
+                return None;
+            }
+            (pressed.take().unwrap_or(0), true)
+        }
+        MouseEvent::Hold(_, _) => {
+            if tracking < 1002 {
+                return None;
+            }
+            (pressed.unwrap_or(0) + 32, false)
+        }
+    };
+
+    if modes.sgr_mouse() {
+        Some(format!("\x1b[<{};{};{}{}", button, x, y, if release { 'm' } else { 'M' }).into_bytes())
+    } else {
+        // the legacy encoding can't address cells beyond 223
+        if x > 223 || y > 223 {
+            return None;
+        }
+        let button = if release { 3 } else { button };
+        Some(vec![ 0x1b, b'[', b'M', 32 + button, 32 + x as u8, 32 + y as u8 ])
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrows() {
+        assert_eq!(encode_key(&Key::Up, false), Some(b"\x1b[A".to_vec()));
+        assert_eq!(encode_key(&Key::Down, false), Some(b"\x1b[B".to_vec()));
+        assert_eq!(encode_key(&Key::Right, false), Some(b"\x1b[C".to_vec()));
+        assert_eq!(encode_key(&Key::Left, false), Some(b"\x1b[D".to_vec()));
+        assert_eq!(encode_key(&Key::Home, false), Some(b"\x1b[H".to_vec()));
+        assert_eq!(encode_key(&Key::End, false), Some(b"\x1b[F".to_vec()));
+    }
+
+    #[test]
+    fn app_cursor() {
+        assert_eq!(encode_key(&Key::Up, true), Some(b"\x1bOA".to_vec()));
+        assert_eq!(encode_key(&Key::Left, true), Some(b"\x1bOD".to_vec()));
+        assert_eq!(encode_key(&Key::Home, true), Some(b"\x1bOH".to_vec()));
+
+        // only cursor keys change
+        assert_eq!(encode_key(&Key::PageUp, true), Some(b"\x1b[5~".to_vec()));
+        assert_eq!(encode_key(&Key::Char('x'), true), Some(b"x".to_vec()));
+    }
+
+    #[test]
+    fn function_keys() {
+        assert_eq!(encode_key(&Key::F(1), false), Some(b"\x1bOP".to_vec()));
+        assert_eq!(encode_key(&Key::F(4), false), Some(b"\x1bOS".to_vec()));
+        assert_eq!(encode_key(&Key::F(5), false), Some(b"\x1b[15~".to_vec()));
+        assert_eq!(encode_key(&Key::F(6), false), Some(b"\x1b[17~".to_vec()));
+        assert_eq!(encode_key(&Key::F(10), false), Some(b"\x1b[21~".to_vec()));
+        assert_eq!(encode_key(&Key::F(11), false), Some(b"\x1b[23~".to_vec()));
+        assert_eq!(encode_key(&Key::F(12), false), Some(b"\x1b[24~".to_vec()));
+        assert_eq!(encode_key(&Key::F(13), false), None);
+    }
+
+    #[test]
+    fn alt_and_ctrl() {
+        assert_eq!(encode_key(&Key::Alt('b'), false), Some(b"\x1bb".to_vec()));
+        assert_eq!(encode_key(&Key::Alt('ä'), false), Some("\x1bä".as_bytes().to_vec()));
+        assert_eq!(encode_key(&Key::Ctrl('c'), false), Some(vec![ 0x03 ]));
+        assert_eq!(encode_key(&Key::Ctrl('['), false), Some(vec![ 0x1b ]));
+        assert_eq!(encode_key(&Key::Ctrl('!'), false), None);
+    }
+
+    #[test]
+    fn editing_keys() {
+        assert_eq!(encode_key(&Key::Char('\n'), false), Some(b"\r".to_vec()));
+        assert_eq!(encode_key(&Key::Backspace, false), Some(vec![ 0x7f ]));
+        assert_eq!(encode_key(&Key::Delete, false), Some(b"\x1b[3~".to_vec()));
+        assert_eq!(encode_key(&Key::BackTab, false), Some(b"\x1b[Z".to_vec()));
+    }
+
+    fn mouse_modes(tracking: u32, sgr: bool) -> TermModes {
+        let modes = TermModes::default();
+        modes.set_private_mode(tracking, true);
+        modes.set_private_mode(1006, sgr);
+        modes
+    }
+
+    #[test]
+    fn mouse_off() {
+        let modes = TermModes::default();
+        let mut pressed = None;
+        assert_eq!(encode_mouse(&MouseEvent::Press(MouseButton::Left, 3, 4), 3, 4, &modes, &mut pressed), None);
+    }
+
+    #[test]
+    fn mouse_sgr() {
+        let modes = mouse_modes(1002, true);
+        let mut pressed = None;
+
+        assert_eq!(encode_mouse(&MouseEvent::Press(MouseButton::Left, 0, 0), 3, 4, &modes, &mut pressed),
+                   Some(b"\x1b[<0;3;4M".to_vec()));
+        assert_eq!(encode_mouse(&MouseEvent::Hold(0, 0), 5, 4, &modes, &mut pressed),
+                   Some(b"\x1b[<32;5;4M".to_vec()));
+        assert_eq!(encode_mouse(&MouseEvent::Release(0, 0), 5, 4, &modes, &mut pressed),
+                   Some(b"\x1b[<0;5;4m".to_vec()));
+        assert_eq!(encode_mouse(&MouseEvent::Press(MouseButton::WheelDown, 0, 0), 300, 2, &modes, &mut pressed),
+                   Some(b"\x1b[<65;300;2M".to_vec()));
+        assert_eq!(pressed, None);
+    }
+
+    #[test]
+    fn mouse_legacy() {
+        let modes = mouse_modes(1000, false);
+        let mut pressed = None;
+
+        assert_eq!(encode_mouse(&MouseEvent::Press(MouseButton::Right, 0, 0), 1, 1, &modes, &mut pressed),
+                   Some(vec![ 0x1b, b'[', b'M', 32 + 2, 33, 33 ]));
+        assert_eq!(pressed, Some(2));
+
+        // no motion reports below 1002
+        assert_eq!(encode_mouse(&MouseEvent::Hold(0, 0), 2, 1, &modes, &mut pressed), None);
+
+        assert_eq!(encode_mouse(&MouseEvent::Release(0, 0), 2, 1, &modes, &mut pressed),
+                   Some(vec![ 0x1b, b'[', b'M', 32 + 3, 34, 33 ]));
+
+        // out of range for the legacy encoding
+        assert_eq!(encode_mouse(&MouseEvent::Press(MouseButton::Left, 0, 0), 224, 1, &modes, &mut pressed), None);
+    }
+
+    #[test]
+    fn mouse_x10() {
+        let modes = mouse_modes(9, false);
+        let mut pressed = None;
+
+        assert_eq!(encode_mouse(&MouseEvent::Press(MouseButton::Left, 0, 0), 10, 20, &modes, &mut pressed),
+                   Some(vec![ 0x1b, b'[', b'M', 32, 42, 52 ]));
+        assert_eq!(encode_mouse(&MouseEvent::Release(0, 0), 10, 20, &modes, &mut pressed), None);
+    }
+
+    #[test]
+    fn sniffer_sets_modes() {
+        let modes = std::sync::Arc::new(TermModes::default());
+        let mut out = Vec::new();
+        ModeSniffer::new(&b"a\x1b[?1h\x1b[?1000;1006hb"[..], modes.clone())
+            .read_to_end(&mut out).unwrap();
+
+        assert_eq!(out, b"a\x1b[?1h\x1b[?1000;1006hb".to_vec());
+        assert!(modes.app_cursor());
+        assert_eq!(modes.mouse_tracking(), 1000);
+        assert!(modes.sgr_mouse());
+
+        ModeSniffer::new(&b"\x1b[?1l"[..], modes.clone())
+            .read_to_end(&mut out).unwrap();
+        assert!(!modes.app_cursor());
+    }
+}
@@ -0,0 +1,186 @@
+use {
+    cgmath::{Point2, Vector2},
+    r3vi::{
+        view::{OuterViewPort, index::*, sequence::*},
+        buffer::index_hashmap::*
+    },
+    nested::terminal::{TerminalEvent, TerminalView},
+    std::sync::Mutex,
+    std::sync::atomic::{AtomicI16, AtomicUsize, Ordering}
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+static TERM_WIDTH: AtomicI16 = AtomicI16::new(80);
+static TERM_HEIGHT: AtomicI16 = AtomicI16::new(40);
+
+/// the single cell the REPL's compositor shows,
+/// either the structured view or a fullscreen PTY
+struct Root {
+    slot: IndexBuffer<Point2<i16>, OuterViewPort<dyn TerminalView>>,
+    structured: OuterViewPort<dyn TerminalView>,
+}
+
+static ROOT: Mutex<Option<Root>> = Mutex::new(None);
+static FULLSCREEN: Mutex<Option<crate::pty::PTYInput>> = Mutex::new(None);
+
+/// the rows above the list of commands, one segment per command
+/// and the rows below the list
+struct CommandList {
+    header: Vec<OuterViewPort<dyn TerminalView>>,
+    segments: OuterViewPort<dyn SequenceView<Item = OuterViewPort<dyn TerminalView>>>,
+    footer: Vec<OuterViewPort<dyn TerminalView>>,
+}
+
+static COMMANDS: Mutex<Option<CommandList>> = Mutex::new(None);
+
+/// index of the command under the cursor
+static FOCUS: AtomicUsize = AtomicUsize::new(0);
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+pub fn set_terminal_size(size: Vector2<i16>) {
+    TERM_WIDTH.store(size.x, Ordering::Relaxed);
+    TERM_HEIGHT.store(size.y, Ordering::Relaxed);
+}
+
+pub fn terminal_size() -> Vector2<i16> {
+    Vector2::new(
+        TERM_WIDTH.load(Ordering::Relaxed),
+        TERM_HEIGHT.load(Ordering::Relaxed)
+    )
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// columns and rows which `view` covers, counted from (0,0)
+fn extent(view: &OuterViewPort<dyn TerminalView>) -> Vector2<i16> {
+    match view.get_view().area() {
+        IndexArea::Range(range) => Vector2::new(range.end().x + 1, range.end().y + 1),
+        IndexArea::Set(points) => points.iter().fold(
+            Vector2::new(0, 0),
+            |e, p| Vector2::new(e.x.max(p.x + 1), e.y.max(p.y + 1))
+        ),
+        _ => Vector2::new(0, 0)
+    }
+}
+
+/// tells the layout where the REPL shows its commands.
+/// `header` are the rows above the list and `footer` the rows below it,
+/// each segment follows a `[n]` label and a separator row.
+pub fn init_command_list(
+    header: Vec<OuterViewPort<dyn TerminalView>>,
+    segments: OuterViewPort<dyn SequenceView<Item = OuterViewPort<dyn TerminalView>>>,
+    footer: Vec<OuterViewPort<dyn TerminalView>>
+) {
+    *COMMANDS.lock().unwrap() = Some(CommandList { header, segments, footer });
+}
+
+pub fn focus_command(n: usize) {
+    FOCUS.store(n, Ordering::Relaxed);
+}
+
+/// screen position of the top left corner of the `n`-th command,
+/// where its output box begins
+pub fn command_origin(n: usize) -> Point2<i16> {
+    let commands = COMMANDS.lock().unwrap();
+    let commands = match commands.as_ref() {
+        Some(commands) => commands,
+        None => { return Point2::new(0, 0); }
+    };
+
+    let segments = commands.segments.get_view();
+    let y = commands.header.iter().map(|view| extent(view).y).sum::<i16>()
+        + (0..n)
+            .filter_map(|k| segments.get(&k))
+            .map(|segment| extent(&segment).y + 1)
+            .sum::<i16>();
+
+    Point2::new(format!("[{}]", n).len() as i16, y)
+}
+
+/// rows below the `n`-th command: the commands after it and the footer
+fn rows_below(n: usize) -> i16 {
+    let commands = COMMANDS.lock().unwrap();
+    let commands = match commands.as_ref() {
+        Some(commands) => commands,
+        None => { return 0; }
+    };
+
+    let segments = commands.segments.get_view();
+    let len = segments.len().unwrap_or(0);
+    commands.footer.iter().map(|view| extent(view).y).sum::<i16>()
+        + (n + 1 .. len)
+            .filter_map(|k| segments.get(&k))
+            .map(|segment| extent(&segment).y + 1)
+            .sum::<i16>()
+}
+
+/// screen position of the output box of the command under the cursor
+pub fn output_origin() -> Point2<i16> {
+    command_origin(FOCUS.load(Ordering::Relaxed))
+}
+
+/// space inside the border of the output box of the command under the cursor,
+/// from its origin to the right edge and down to the rows below it
+pub fn output_size() -> Vector2<i16> {
+    let n = FOCUS.load(Ordering::Relaxed);
+    let size = terminal_size();
+    let origin = command_origin(n);
+
+    Vector2::new(
+        (size.x - origin.x - 2).max(10),
+        (size.y - origin.y - rows_below(n) - 2).max(5)
+    )
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// returns the view to push into the REPL's compositor
+pub fn init_root(structured: OuterViewPort<dyn TerminalView>) -> OuterViewPort<dyn TerminalView> {
+    let mut slot = IndexBuffer::new();
+    slot.insert(Point2::new(0, 0), structured.clone());
+    let view = slot.get_port().flatten();
+
+    *ROOT.lock().unwrap() = Some(Root { slot, structured });
+    view
+}
+
+/// shows only `view` at full terminal size and sends all input to the PTY
+pub fn enter_fullscreen(view: OuterViewPort<dyn TerminalView>, mut input: crate::pty::PTYInput) {
+    if let Some(root) = ROOT.lock().unwrap().as_mut() {
+        root.slot.insert(Point2::new(0, 0), view);
+    }
+
+    input.set_origin(Point2::new(0, 0));
+    input.resize(terminal_size());
+    *FULLSCREEN.lock().unwrap() = Some(input);
+}
+
+/// restores the structured view
+pub fn leave_fullscreen() {
+    if let Some(input) = FULLSCREEN.lock().unwrap().take() {
+        input.resize(output_size());
+    }
+
+    if let Some(root) = ROOT.lock().unwrap().as_mut() {
+        let structured = root.structured.clone();
+        root.slot.insert(Point2::new(0, 0), structured);
+    }
+}
+
+pub fn resize_fullscreen() {
+    if let Some(input) = FULLSCREEN.lock().unwrap().as_ref() {
+        input.resize(terminal_size());
+    }
+}
+
+/// forwards `event` to the fullscreen PTY, if there is one
+pub fn fullscreen_input(event: &TerminalEvent) -> bool {
+    if let Some(input) = FULLSCREEN.lock().unwrap().as_ref() {
+        input.handle_terminal_event(event).ok();
+        true
+    } else {
+        false
+    }
+}
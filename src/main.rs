@@ -9,6 +9,21 @@ mod pipeline;
 mod command;
 mod process_types;
 mod interactive;
+mod parse;
+mod export;
+mod stream;
+mod job;
+mod reaper;
+mod layout;
+mod keys;
+mod which;
+mod builtin;
+mod typedb;
+mod env;
+mod complete;
+mod arg;
+mod substitution;
+mod quotes;
 
 use {
     clap::{Parser},
@@ -33,6 +48,9 @@ use {
 pub fn init_os_ctx(parent: Arc<RwLock<Context>>) -> Arc<RwLock<Context>> {
     let ctx = Arc::new(RwLock::new(Context::with_parent(Some(parent))));
     crate::path::init_ctx(&mut ctx.write().unwrap());
+    crate::env::init_ctx(&mut ctx.write().unwrap());
+    crate::substitution::init_ctx(&mut ctx.write().unwrap());
+    crate::quotes::init_ctx(&mut ctx.write().unwrap());
 
     crate::process::ProcessLauncher::init_ctx(&mut ctx.write().unwrap());
     crate::pipeline::PipelineLauncher::init_ctx(&mut ctx.write().unwrap());
@@ -61,28 +79,222 @@ pub fn init_os_ctx(parent: Arc<RwLock<Context>>) -> Arc<RwLock<Context>> {
 struct Cli {
     #[arg(short, long)]
     check_expr: Option<String>,
+
+    /// run a pipeline non-interactively and exit with its status
+    #[arg(short = 'c', long)]
+    command: Option<String>,
+
+    /// check the syntax of every pipeline in a script or history file
+    #[arg(long)]
+    check_file: Option<String>,
+
+    /// parse a pipeline and print it back as quoted sh text
+    #[arg(long)]
+    to_sh: Option<String>,
+
+    /// milliseconds to wait after interrupting a pipeline before sending SIGTERM
+    #[arg(long, default_value_t = 2000)]
+    sigint_timeout: u64,
+
+    /// milliseconds to wait after SIGTERM before killing a pipeline
+    #[arg(long, default_value_t = 3000)]
+    sigterm_timeout: u64,
+
+    /// history file whose pipelines are loaded into the command list
+    #[arg(long)]
+    history: Option<String>,
+}
+
+fn print_diagnostics(node: &nested::tree::NestedNode) {
+    let diagnostics_port = node.diag.clone().unwrap();
+    for message in diagnostics_port.get_view().unwrap().iter() {
+        for x in message.addr.iter() {
+            print!("{}.", x);
+        }
+
+        let view_port = message.port.clone();
+        view_port.0.update();
+        let view = view_port.get_view();
+
+        match view.area() {
+            IndexArea::Range(r) => {
+                let mut last_y = None;
+                for pos in GridWindowIterator::from(r) {
+
+                    last_y = match last_y {
+                        None => Some(pos.y),
+                        Some(mut last_y) => {
+                            while pos.y > last_y {
+                                last_y += 1;
+                                print!("\n");
+                            }
+                            Some(pos.y)
+                        }
+                    };
+
+                    if let Some(atom) = view.get(&pos) {
+                        print!(
+                            "{}{}",
+                            atom.style,
+                            atom.c.unwrap_or(' ')
+                        );
+                    } else {
+                        print!(" ");
+                    }
+                }
+            }
+            area => {
+                eprintln!("area: {:?}", area);
+            }
+        }
+
+        println!("{}", termion::style::Reset);
+    }
+}
+
+fn read_script(path: &str) -> Vec<(usize, String)> {
+    match std::fs::read_to_string(path) {
+        Ok(src) => parse::split_script(&src),
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// runs a pipeline with the terminal as stdin/stdout and returns the exit code of the last stage
+fn run_command(src: &str) -> i32 {
+    let mut pipeline = match parse::parse_pipeline(src) {
+        Ok(pipeline) => pipeline,
+        Err(err) => {
+            eprintln!("syntax error, {}", err);
+            eprintln!("{}", err.show(src));
+            return 2;
+        }
+    };
+
+    pipeline.expand_aliases();
+    let strings = pipeline.get_strings();
+
+    if strings.len() == 1 {
+        let mut sh = builtin::Shell::new(None);
+        if let Some(result) = builtin::run(&strings[0], &mut sh) {
+            print!("{}", sh.stdout);
+            return match result {
+                Ok(()) => builtin::exit_requested().unwrap_or(0),
+                Err(err) => {
+                    eprintln!("{}: {}", strings[0][0], err);
+                    1
+                }
+            };
+        }
+    }
+
+    let mut children = Vec::new();
+    let mut last_stdout : Option<std::fs::File> = None;
+    let n = strings.len();
+
+    for (j, process) in pipeline.processes.iter().enumerate() {
+        let merge_stderr = process.merges_stderr();
+        let (vars, argv) = process.expand();
+
+        if argv.len() == 0 {
+            continue;
+        }
+        match which::check(&argv[0], None) {
+            Ok(which::Resolution::Builtin) => {
+                eprintln!("{}: builtins can't run in a pipeline", argv[0]);
+                return 126;
+            }
+            Ok(_) => {}
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 127;
+            }
+        }
+
+        let mut cmd = std::process::Command::new(&argv[0]);
+        cmd.args(&argv[1..]);
+        cmd.env_clear();
+        cmd.envs(crate::env::vars());
+        cmd.envs(vars);
+
+        if let Some(prev) = last_stdout.take() {
+            cmd.stdin(prev);
+        }
+
+        let spawned = if j + 1 < n {
+            subprocess::make_pipe().and_then(|(read, write)| {
+                cmd.stdout(write.try_clone()?);
+                if merge_stderr {
+                    cmd.stderr(write);
+                }
+                last_stdout = Some(read);
+                cmd.spawn()
+            })
+        } else {
+            if merge_stderr {
+                use std::os::unix::io::{AsRawFd, FromRawFd};
+                let fd = unsafe { libc::dup(std::io::stdout().as_raw_fd()) };
+                if fd >= 0 {
+                    cmd.stderr(unsafe { std::process::Stdio::from_raw_fd(fd) });
+                }
+            }
+            cmd.spawn()
+        };
+
+        match spawned {
+            Ok(child) => children.push(child),
+            Err(err) => {
+                eprintln!("{}: {}", argv[0], err);
+                return 126;
+            }
+        }
+        // drop our copy of the pipe's write end, so the next stage sees EOF
+        drop(cmd);
+    }
+
+    let mut code = 0;
+    for mut child in children {
+        code = match child.wait() {
+            Ok(status) => {
+                use std::os::unix::process::ExitStatusExt;
+                status.code().unwrap_or(128 + status.signal().unwrap_or(0))
+            }
+            Err(_) => 1
+        };
+    }
+    code
 }
 
 #[async_std::main]
 async fn main() {    
     let cli = Cli::parse();
 
+    pipeline::set_cancel_timeouts(
+        std::time::Duration::from_millis(cli.sigint_timeout),
+        std::time::Duration::from_millis(cli.sigterm_timeout)
+    );
+
+    builtin::init();
+
+    if let Some(src) = cli.command.as_deref() {
+        std::process::exit(run_command(src));
+    }
+
     // Type Context //
     let ctx = Arc::new(RwLock::new(Context::default()));
     let ctx = init_os_ctx(ctx);
 
     if let Some(check_expr) = cli.check_expr.as_deref() {
-        let mut node = Context::make_node(&ctx, Context::parse(&ctx, "Pipeline"), SingletonBuffer::new(0).get_port()).unwrap();
-        
-        node.goto(TreeCursor::home());
-        for c in check_expr.chars() {
-            node.send_cmd_obj(
-                ReprTree::new_leaf(
-                    Context::parse(&ctx, "Char"),
-                    AnyOuterViewPort::from(SingletonBuffer::new(c).get_port())
-                )
-            );
-        }
+        let node = match parse::make_pipeline_node(&ctx, check_expr, SingletonBuffer::new(0).get_port()) {
+            Ok(node) => node,
+            Err(err) => {
+                eprintln!("syntax error, {}", err);
+                eprintln!("{}", err.show(check_expr));
+                std::process::exit(1);
+            }
+        };
 
         let view_port = node.view.clone().unwrap();
         view_port.0.update();
@@ -91,54 +303,46 @@ async fn main() {
         let mut pipeline = pipeline.write().unwrap();
         pipeline.typecheck();
 
-        let diagnostics_port = node.diag.unwrap();
-        for message in diagnostics_port.get_view().unwrap().iter() {
-            for x in message.addr.iter() {
-                print!("{}.", x);
-            }
+        print_diagnostics(&node);
 
-            let view_port = message.port.clone();
-            view_port.0.update();
-            let view = view_port.get_view();
-
-            match view.area() {
-                IndexArea::Range(r) => {
-                    let mut last_y = None;
-                    for pos in GridWindowIterator::from(r) {
-
-                        last_y = match last_y {
-                            None => Some(pos.y),
-                            Some(mut last_y) => {
-                                while pos.y > last_y {
-                                    last_y += 1;
-                                    print!("\n");
-                                }
-                                Some(pos.y)
-                            }
-                        };
-                        
-                        if let Some(atom) = view.get(&pos) {
-                            print!(
-                                "{}{}",
-                                atom.style,
-                                atom.c.unwrap_or(' ')
-                            );
-                        } else {
-                            print!(" ");
-                        }
-                    }
-                }
-                area => {
-                    eprintln!("area: {:?}", area);
-                }
+        println!("---");
+    } else if let Some(expr) = cli.to_sh.as_deref() {
+        match parse::make_pipeline_node(&ctx, expr, SingletonBuffer::new(0).get_port()) {
+            Ok(node) => {
+                let pipeline = node.get_edit::<PipelineLauncher>().unwrap();
+                println!("{}", pipeline.read().unwrap().to_sh());
             }
+            Err(err) => {
+                eprintln!("syntax error, {}", err);
+                eprintln!("{}", err.show(expr));
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(path) = cli.check_file.as_deref() {
+        let mut ok = true;
 
-            println!("{}", termion::style::Reset);
+        for (line, src) in read_script(path) {
+            if let Err(err) = parse::parse_pipeline(&src) {
+                let (l, c) = err.line_col(&src);
+                eprintln!("{}:{}:{}: {}", path, line + l, c + 1, err.msg);
+                eprintln!("{}", err.show(&src));
+                ok = false;
+            }
         }
 
-        println!("---");
+        if !ok {
+            std::process::exit(1);
+        }
     } else {
-        interactive::tui_repl(ctx).await;
+        let history = match cli.history.as_deref() {
+            Some(path) => read_script(path).into_iter().map(|(_, src)| src).collect(),
+            None => Vec::new()
+        };
+
+        interactive::tui_repl(ctx, history).await;
+
+        if let Some(code) = builtin::exit_requested() {
+            std::process::exit(code);
+        }
     }
 }
-
@@ -0,0 +1,940 @@
+use {
+    r3vi::{
+        view::{OuterViewPort, singleton::*}
+    },
+    nested::{
+        type_system::{Context, ReprTree},
+        editors::list::ListEditor,
+        tree::{NestedNode, TreeNav, TreeCursor},
+        commander::ObjCommander
+    },
+    std::sync::{Arc, RwLock},
+    crate::pipeline::PipelineLauncher
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WordPart {
+    Literal(String),
+    SingleQuoted(String),
+    DoubleQuoted(Vec<WordPart>),
+    Variable(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Word {
+    pub pos: usize,
+    pub parts: Vec<WordPart>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Redirect {
+    /// `2>&1`
+    StderrToStdout,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Process {
+    pub pos: usize,
+
+    /// leading `NAME=value` words
+    pub assignments: Vec<(String, Word)>,
+
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pipeline {
+    pub processes: Vec<Process>,
+
+    /// trailing `&`
+    pub background: bool,
+}
+
+/// syntax error at character offset `pos` of the parsed string
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub pos: usize,
+    pub msg: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "col {}: {}", self.pos + 1, self.msg)
+    }
+}
+
+impl ParseError {
+    fn new(pos: usize, msg: &str) -> Self {
+        ParseError { pos, msg: msg.into() }
+    }
+
+    /// zero-based line and column of the error in `src`
+    pub fn line_col(&self, src: &str) -> (usize, usize) {
+        let mut line = 0;
+        let mut col = 0;
+        for c in src.chars().take(self.pos) {
+            if c == '\n' {
+                line += 1;
+                col = 0;
+            } else {
+                col += 1;
+            }
+        }
+        (line, col)
+    }
+
+    /// the offending line followed by a caret under the error position
+    pub fn show(&self, src: &str) -> String {
+        let (line, col) = self.line_col(src);
+        format!("{}\n{}^ {}",
+                src.lines().nth(line).unwrap_or(""),
+                " ".repeat(col),
+                self.msg)
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+impl Word {
+    /// the text of a word which consists of one unquoted literal
+    pub fn as_literal(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Literal(l)] => Some(l.as_str()),
+            _ => None
+        }
+    }
+
+    /// splits a word which starts with an unquoted `NAME=`
+    /// into the name and the value
+    pub fn as_assignment(&self) -> Option<(String, Word)> {
+        let first = match self.parts.first() {
+            Some(WordPart::Literal(l)) => l,
+            _ => { return None; }
+        };
+        let (name, rest) = split_assignment(first)?;
+
+        let mut parts = Vec::new();
+        if rest.len() > 0 {
+            parts.push(WordPart::Literal(rest.into()));
+        }
+        parts.extend(self.parts[1..].iter().cloned());
+
+        Some((name.into(), Word { pos: self.pos + name.len() + 1, parts }))
+    }
+
+    /// the word `NAME=value`
+    pub fn assignment(name: &str, value: &Word) -> Word {
+        let mut parts = vec![ WordPart::Literal(format!("{}=", name)) ];
+        for part in value.parts.iter() {
+            match (parts.last_mut(), part) {
+                (Some(WordPart::Literal(l)), WordPart::Literal(r)) => l.push_str(r),
+                _ => parts.push(part.clone())
+            }
+        }
+        Word { pos: value.pos.saturating_sub(name.len() + 1), parts }
+    }
+
+    /// concatenation of all parts with quotes removed,
+    /// substitutions are kept in their textual form
+    pub fn to_arg(&self) -> String {
+        fn push_parts(s: &mut String, parts: &Vec<WordPart>) {
+            for part in parts.iter() {
+                match part {
+                    WordPart::Literal(l) |
+                    WordPart::SingleQuoted(l) => s.push_str(l),
+                    WordPart::DoubleQuoted(inner) => push_parts(s, inner),
+                    WordPart::Variable(name) => { s.push('$'); s.push_str(name); }
+                }
+            }
+        }
+
+        let mut s = String::new();
+        push_parts(&mut s, &self.parts);
+        s
+    }
+
+    /// the argument as passed to a program, with variables substituted
+    /// from the shell environment. also returns the names of undefined ones.
+    pub fn expand(&self) -> (String, Vec<String>) {
+        fn push_parts(s: &mut String, undefined: &mut Vec<String>, parts: &Vec<WordPart>) {
+            for part in parts.iter() {
+                match part {
+                    WordPart::Literal(l) |
+                    WordPart::SingleQuoted(l) => s.push_str(l),
+                    WordPart::DoubleQuoted(inner) => push_parts(s, undefined, inner),
+                    WordPart::Variable(name) => match special_parameter(name).or_else(|| crate::env::get(name)) {
+                        Some(value) => s.push_str(&value),
+                        None => undefined.push(name.clone())
+                    },
+                }
+            }
+        }
+
+        let mut s = String::new();
+        let mut undefined = Vec::new();
+        push_parts(&mut s, &mut undefined, &self.parts);
+        (s, undefined)
+    }
+}
+
+impl Redirect {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Redirect::StderrToStdout => "2>&1"
+        }
+    }
+
+    /// the redirect written as an unquoted word
+    pub fn from_word(word: &Word) -> Option<Redirect> {
+        match word.as_literal() {
+            Some("2>&1") => Some(Redirect::StderrToStdout),
+            _ => None
+        }
+    }
+}
+
+impl Process {
+    /// a process from the words of an editor, empty words are skipped.
+    /// unquoted assignments before the command and redirects
+    /// are taken out of the arguments
+    pub fn from_words(words: Vec<Word>) -> Process {
+        let mut process = Process {
+            pos: 0,
+            assignments: Vec::new(),
+            words: Vec::new(),
+            redirects: Vec::new()
+        };
+
+        for word in words.into_iter().filter(|word| word.parts.len() > 0) {
+            process.push_word(word);
+        }
+
+        process
+    }
+
+    fn push_word(&mut self, word: Word) {
+        if let Some(redirect) = Redirect::from_word(&word) {
+            self.redirects.push(redirect);
+        } else if let (0, Some(assignment)) = (self.words.len(), word.as_assignment()) {
+            self.assignments.push(assignment);
+        } else {
+            self.words.push(word);
+        }
+    }
+
+    /// replaces an alias in command position by the processes of its value.
+    /// the arguments go to the last of them, an alias is not expanded inside itself.
+    pub fn expand_aliases(mut self) -> Vec<Process> {
+        let mut head = Vec::new();
+        let mut seen = Vec::new();
+
+        loop {
+            let name = match self.words.first().and_then(|word| word.as_literal()) {
+                Some(name) if !seen.iter().any(|s| s == name) => name.to_string(),
+                _ => { break; }
+            };
+            let mut alias = match crate::which::get_alias(&name).map(|value| parse_pipeline(&value)) {
+                Some(Ok(alias)) if alias.processes.len() > 0 && !alias.background => alias,
+                _ => { break; }
+            };
+
+            let mut last = alias.processes.pop().unwrap();
+            last.pos = self.pos;
+            last.assignments.splice(0..0, self.assignments.drain(..));
+            last.words.extend(self.words.drain(1..));
+            last.redirects.extend(self.redirects.drain(..));
+
+            head.extend(alias.processes);
+            seen.push(name);
+            self = last;
+        }
+
+        head.push(self);
+        head
+    }
+
+    /// the assigned variables and argv, with variables substituted
+    pub fn expand(&self) -> (Vec<(String, String)>, Vec<String>) {
+        (
+            self.assignments.iter().map(|(name, value)| (name.clone(), value.expand().0)).collect(),
+            self.words.iter().map(|word| word.expand().0).collect()
+        )
+    }
+
+    pub fn merges_stderr(&self) -> bool {
+        self.redirects.contains(&Redirect::StderrToStdout)
+    }
+}
+
+impl Pipeline {
+    /// a pipeline from the words of an editor, empty words are skipped.
+    /// an unquoted `&` as last word sends it to the background.
+    pub fn from_words(processes: Vec<Vec<Word>>) -> Pipeline {
+        let mut processes : Vec<Process> = processes.into_iter()
+            .map(Process::from_words)
+            .filter(|process| process.words.len() > 0)
+            .collect();
+
+        let mut background = false;
+        if let Some(last) = processes.last_mut() {
+            if last.words.last().and_then(|word| word.as_literal()) == Some("&") {
+                last.words.pop();
+                background = true;
+            }
+        }
+        processes.retain(|process| process.words.len() > 0);
+
+        Pipeline { processes, background }
+    }
+
+    /// replaces aliases in command position, see `Process::expand_aliases`
+    pub fn expand_aliases(&mut self) {
+        self.processes = self.processes.drain(..)
+            .flat_map(|process| process.expand_aliases())
+            .collect();
+    }
+
+    /// argv of every process, with variables substituted.
+    /// redirects are not part of it
+    pub fn get_strings(&self) -> Vec<Vec<String>> {
+        self.processes.iter()
+            .map(|p| p.expand().1)
+            .collect()
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+fn is_operator(c: char) -> bool {
+    "|&;<>()\n".contains(c)
+}
+
+fn is_name_start(c: char) -> bool {
+    c == '_' || c.is_ascii_alphabetic()
+}
+
+/// char of a variable name
+pub fn is_name_char(c: char) -> bool {
+    c == '_' || c.is_ascii_alphanumeric()
+}
+
+impl Parser {
+    fn new(s: &str) -> Self {
+        Parser {
+            chars: s.chars().collect(),
+            pos: 0
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).cloned()
+    }
+
+    /// skips blanks, comments and escaped newlines.
+    /// if `newlines` is set, unescaped newlines are skipped as well.
+    fn skip_blanks(&mut self, newlines: bool) {
+        loop {
+            match self.peek() {
+                Some(c) if is_blank(c) => { self.pos += 1; }
+                Some('\n') if newlines => { self.pos += 1; }
+                Some('\\') if self.peek_at(1) == Some('\n') => { self.pos += 2; }
+                Some('#') => {
+                    while let Some(c) = self.peek() {
+                        if c == '\n' { break; }
+                        self.pos += 1;
+                    }
+                }
+                _ => { break; }
+            }
+        }
+    }
+
+    /// a newline followed by a `|` continues the pipeline on the next line
+    fn is_continuation(&self) -> bool {
+        let mut i = self.pos;
+        while let Some(c) = self.chars.get(i) {
+            if *c == '|' {
+                return self.chars.get(i + 1) != Some(&'|');
+            } else if is_blank(*c) || *c == '\n' {
+                i += 1;
+            } else {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        let mut processes = Vec::new();
+        let mut background = false;
+
+        self.skip_blanks(true);
+        if self.peek().is_none() {
+            return Ok(Pipeline { processes, background: false });
+        }
+
+        loop {
+            let process = self.parse_process()?;
+
+            if process.words.len() == 0 {
+                return Err(ParseError::new(process.pos, "expected command"));
+            }
+            processes.push(process);
+
+            if self.peek() == Some('\n') && self.is_continuation() {
+                self.skip_blanks(true);
+            }
+
+            match self.peek() {
+                None => { break; }
+                Some('|') => {
+                    if self.peek_at(1) == Some('|') {
+                        return Err(ParseError::new(self.pos, "`||` lists are not supported"));
+                    }
+                    self.pos += 1;
+                    self.skip_blanks(true);
+                }
+                Some('\n') => {
+                    self.skip_blanks(true);
+                    if self.peek().is_some() {
+                        return Err(ParseError::new(self.pos, "expected end of pipeline"));
+                    }
+                    break;
+                }
+                Some('&') => {
+                    if self.peek_at(1) == Some('&') {
+                        return Err(ParseError::new(self.pos, "`&&` lists are not supported"));
+                    }
+                    self.pos += 1;
+                    self.skip_blanks(true);
+                    if self.peek().is_some() {
+                        return Err(ParseError::new(self.pos, "expected end of pipeline after `&`"));
+                    }
+                    background = true;
+                    break;
+                }
+                Some(';') => {
+                    return Err(ParseError::new(self.pos, "command lists are not supported"));
+                }
+                Some('<') | Some('>') => {
+                    return Err(ParseError::new(self.pos, "redirections are not supported"));
+                }
+                Some('(') | Some(')') => {
+                    return Err(ParseError::new(self.pos, "subshells are not supported"));
+                }
+                Some(_) => {
+                    return Err(ParseError::new(self.pos, "unexpected character"));
+                }
+            }
+
+            if self.peek().is_none() {
+                return Err(ParseError::new(self.pos, "expected command after `|`"));
+            }
+        }
+
+        Ok(Pipeline { processes, background })
+    }
+
+    fn parse_process(&mut self) -> Result<Process, ParseError> {
+        self.skip_blanks(false);
+
+        let mut process = Process {
+            pos: self.pos,
+            assignments: Vec::new(),
+            words: Vec::new(),
+            redirects: Vec::new()
+        };
+
+        loop {
+            self.skip_blanks(false);
+            match self.peek() {
+                None => { break; }
+                Some(c) if is_operator(c) => { break; }
+                Some('2') if self.is_merge_redirect() => {
+                    self.pos += 4;
+                    process.redirects.push(Redirect::StderrToStdout);
+                }
+                Some(_) => {
+                    let word = self.parse_word()?;
+                    process.push_word(word);
+                }
+            }
+        }
+
+        Ok(process)
+    }
+
+    fn is_merge_redirect(&self) -> bool {
+        self.peek_at(1) == Some('>') &&
+            self.peek_at(2) == Some('&') &&
+            self.peek_at(3) == Some('1') &&
+            match self.peek_at(4) {
+                None => true,
+                Some(c) => is_blank(c) || is_operator(c)
+            }
+    }
+
+    fn parse_word(&mut self) -> Result<Word, ParseError> {
+        let mut word = Word {
+            pos: self.pos,
+            parts: Vec::new()
+        };
+
+        while let Some(c) = self.peek() {
+            if is_blank(c) || is_operator(c) {
+                break;
+            }
+
+            match c {
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => { self.pos += 1; }
+                        Some(c) => {
+                            self.pos += 1;
+                            push_literal(&mut word.parts, c);
+                        }
+                        None => {
+                            return Err(ParseError::new(self.pos - 1, "trailing backslash"));
+                        }
+                    }
+                }
+                '\'' => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    let mut s = String::new();
+                    loop {
+                        match self.peek() {
+                            Some('\'') => { self.pos += 1; break; }
+                            Some(c) => { self.pos += 1; s.push(c); }
+                            None => {
+                                return Err(ParseError::new(start, "unterminated single quote"));
+                            }
+                        }
+                    }
+                    word.parts.push(WordPart::SingleQuoted(s));
+                }
+                '"' => {
+                    word.parts.push(self.parse_double_quoted()?);
+                }
+                '$' => {
+                    let part = self.parse_dollar()?;
+                    word.parts.push(part);
+                }
+                '`' => {
+                    return Err(ParseError::new(self.pos, "backquote substitution is not supported"));
+                }
+                c => {
+                    self.pos += 1;
+                    push_literal(&mut word.parts, c);
+                }
+            }
+        }
+
+        Ok(word)
+    }
+
+    fn parse_double_quoted(&mut self) -> Result<WordPart, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+
+        let mut parts = Vec::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => { self.pos += 1; }
+                        Some(c) if "$`\"\\".contains(c) => {
+                            self.pos += 1;
+                            push_literal(&mut parts, c);
+                        }
+                        _ => {
+                            push_literal(&mut parts, '\\');
+                        }
+                    }
+                }
+                Some('$') => {
+                    let part = self.parse_dollar()?;
+                    parts.push(part);
+                }
+                Some('`') => {
+                    return Err(ParseError::new(self.pos, "backquote substitution is not supported"));
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    push_literal(&mut parts, c);
+                }
+                None => {
+                    return Err(ParseError::new(start, "unterminated double quote"));
+                }
+            }
+        }
+
+        Ok(WordPart::DoubleQuoted(parts))
+    }
+
+    fn parse_dollar(&mut self) -> Result<WordPart, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                let mut name = String::new();
+                loop {
+                    match self.peek() {
+                        Some('}') => { self.pos += 1; break; }
+                        Some(c) if is_name_char(c) => { self.pos += 1; name.push(c); }
+                        Some(_) => {
+                            return Err(ParseError::new(self.pos, "invalid character in variable name"));
+                        }
+                        None => {
+                            return Err(ParseError::new(start, "unterminated `${`"));
+                        }
+                    }
+                }
+                if name.len() == 0 {
+                    return Err(ParseError::new(start, "empty variable name"));
+                }
+                Ok(WordPart::Variable(name))
+            }
+            Some('(') => {
+                Err(ParseError::new(start, "command substitution is not supported"))
+            }
+            Some(c) if is_name_start(c) => {
+                let mut name = String::new();
+                while let Some(c) = self.peek() {
+                    if !is_name_char(c) {
+                        break;
+                    }
+                    self.pos += 1;
+                    name.push(c);
+                }
+                Ok(WordPart::Variable(name))
+            }
+            Some(c) if c.is_ascii_digit() || "?$!#@*-".contains(c) => {
+                self.pos += 1;
+                Ok(WordPart::Variable(c.to_string()))
+            }
+            _ => {
+                Ok(WordPart::Literal("$".into()))
+            }
+        }
+    }
+}
+
+fn push_literal(parts: &mut Vec<WordPart>, c: char) {
+    if let Some(WordPart::Literal(s)) = parts.last_mut() {
+        s.push(c);
+    } else {
+        parts.push(WordPart::Literal(c.to_string()));
+    }
+}
+
+pub fn parse_pipeline(s: &str) -> Result<Pipeline, ParseError> {
+    Parser::new(s).parse_pipeline()
+}
+
+/// splits a script or history file into pipelines.
+/// lines starting with `|` continue the previous pipeline.
+/// returns (line number, pipeline source) pairs.
+pub fn split_script(src: &str) -> Vec<(usize, String)> {
+    let mut entries : Vec<(usize, String)> = Vec::new();
+    let mut continued = false;
+
+    for (i, line) in src.lines().enumerate() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with('|') && !trimmed.starts_with("||") && entries.len() > 0 {
+            let last = entries.last_mut().unwrap();
+            last.1.push('\n');
+            last.1.push_str(line);
+        } else if continued {
+            let last = entries.last_mut().unwrap();
+            last.1.push('\n');
+            last.1.push_str(line);
+        } else if trimmed.len() > 0 && !trimmed.starts_with('#') {
+            entries.push((i + 1, line.into()));
+        }
+
+        continued = line.ends_with('\\') || line.trim_end().ends_with('|');
+    }
+
+    entries
+}
+
+/// value of a special parameter like `$$` or `$#`, which is no variable.
+/// there are no positional parameters and no exit status is kept,
+/// so the others are empty.
+pub fn special_parameter(name: &str) -> Option<String> {
+    match name {
+        "$" => Some(std::process::id().to_string()),
+        "#" => Some("0".into()),
+        "?" | "!" | "@" | "*" | "-" => Some(String::new()),
+        _ if name.len() > 0 && name.chars().all(|c| c.is_ascii_digit()) => Some(String::new()),
+        _ => None
+    }
+}
+
+/// splits a word which is taken as a `NAME=value` assignment
+/// if it appears unquoted in command position
+pub fn split_assignment(arg: &str) -> Option<(&str, &str)> {
+    match arg.find('=') {
+        Some(i) if i > 0 => {
+            let name = &arg[..i];
+            if !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+            {
+                Some((name, &arg[i+1..]))
+            } else {
+                None
+            }
+        }
+        _ => None
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+fn make_char_list(
+    ctx: &Arc<RwLock<Context>>,
+    typename: &str,
+    s: &str,
+    depth: OuterViewPort<dyn SingletonView<Item = usize>>
+) -> NestedNode {
+    let mut node = Context::make_node(ctx, Context::parse(ctx, typename), depth).unwrap();
+
+    node.goto(TreeCursor::home());
+    for c in s.chars() {
+        node.send_cmd_obj(ReprTree::from_char(ctx, c));
+    }
+    node.goto(TreeCursor::none());
+
+    node
+}
+
+pub fn make_process_node(
+    ctx: &Arc<RwLock<Context>>,
+    process: &Process,
+    depth: OuterViewPort<dyn SingletonView<Item = usize>>
+) -> NestedNode {
+    let node = Context::make_node(ctx, Context::parse(ctx, "Process"), depth.clone()).unwrap();
+    let list = crate::process::arg_list(&node).unwrap();
+
+    for (name, value) in process.assignments.iter() {
+        let arg_node = crate::arg::make_assignment_node(ctx, name, value, depth.map(|d| d + 1));
+        list.write().unwrap().data.push(arg_node);
+    }
+
+    for word in process.words.iter() {
+        let arg_node = crate::arg::make_arg_node(ctx, word, depth.map(|d| d + 1));
+        list.write().unwrap().data.push(arg_node);
+    }
+
+    for redirect in process.redirects.iter() {
+        let arg_node = make_char_list(ctx, "ProcessArg", redirect.to_str(), depth.map(|d| d + 1));
+        list.write().unwrap().data.push(arg_node);
+    }
+
+    node
+}
+
+/// fills the (empty) list of a `Pipeline` node at `depth` with processes
+pub fn load_pipeline(
+    ctx: &Arc<RwLock<Context>>,
+    pipeline_editor: &NestedNode,
+    pipeline: &Pipeline,
+    depth: OuterViewPort<dyn SingletonView<Item = usize>>
+) {
+    let list = pipeline_editor.get_edit::<ListEditor>().unwrap();
+    let mut list = list.write().unwrap();
+    list.data.clear();
+
+    for process in pipeline.processes.iter() {
+        list.data.push(make_process_node(ctx, process, depth.map(|d| d + 1)));
+    }
+
+    if pipeline.background && list.data.len() > 0 {
+        let process_node = list.data.get(list.data.len() - 1);
+        let process_list = crate::process::arg_list(&process_node).unwrap();
+        process_list.write().unwrap().data.push(
+            make_char_list(ctx, "ProcessArg", "&", depth.map(|d| d + 2))
+        );
+    }
+}
+
+/// parses a shell string into a new `Pipeline` node
+pub fn make_pipeline_node(
+    ctx: &Arc<RwLock<Context>>,
+    s: &str,
+    depth: OuterViewPort<dyn SingletonView<Item = usize>>
+) -> Result<NestedNode, ParseError> {
+    let pipeline = parse_pipeline(s)?;
+
+    let node = Context::make_node(ctx, Context::parse(ctx, "Pipeline"), depth.clone()).unwrap();
+    {
+        let launcher = node.get_edit::<PipelineLauncher>().unwrap();
+        let launcher = launcher.read().unwrap();
+        load_pipeline(ctx, &launcher.editor, &pipeline, depth);
+    }
+
+    Ok(node)
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(process: &Process) -> Vec<String> {
+        process.words.iter().map(|word| word.to_arg()).collect()
+    }
+
+    fn sh(src: &str) -> String {
+        crate::export::pipeline_to_sh(&parse_pipeline(src).unwrap())
+    }
+
+    #[test]
+    fn quoting() {
+        let pipeline = parse_pipeline("echo 'a b' \"c d\" e\\ f").unwrap();
+        assert_eq!(pipeline.processes.len(), 1);
+        assert_eq!(args(&pipeline.processes[0]), vec!["echo", "a b", "c d", "e f"]);
+    }
+
+    #[test]
+    fn pipe_inside_quotes() {
+        let pipeline = parse_pipeline("dc -e \"16 o 10 i @ p\" | wc -l").unwrap();
+        assert_eq!(pipeline.processes.len(), 2);
+        assert_eq!(args(&pipeline.processes[0]), vec!["dc", "-e", "16 o 10 i @ p"]);
+        assert_eq!(args(&pipeline.processes[1]), vec!["wc", "-l"]);
+    }
+
+    #[test]
+    fn assignments_redirects_background() {
+        let pipeline = parse_pipeline("FOO=1 BAR=\"x y\" env 2>&1 | sort &").unwrap();
+        assert!(pipeline.background);
+
+        let env = &pipeline.processes[0];
+        let assignments : Vec<(String, String)> = env.assignments.iter()
+            .map(|(name, value)| (name.clone(), value.to_arg()))
+            .collect();
+        assert_eq!(assignments, vec![("FOO".into(), "1".into()), ("BAR".into(), "x y".into())]);
+        assert_eq!(args(env), vec!["env"]);
+        assert_eq!(env.redirects, vec![Redirect::StderrToStdout]);
+
+        assert_eq!(args(&pipeline.processes[1]), vec!["sort"]);
+    }
+
+    #[test]
+    fn variables() {
+        let pipeline = parse_pipeline("echo ${HOME}x $1 $? '$X'").unwrap();
+        let words = &pipeline.processes[0].words;
+        assert_eq!(words[1].parts, vec![WordPart::Variable("HOME".into()), WordPart::Literal("x".into())]);
+        assert_eq!(words[2].parts, vec![WordPart::Variable("1".into())]);
+        assert_eq!(words[3].parts, vec![WordPart::Variable("?".into())]);
+        assert_eq!(words[4].parts, vec![WordPart::SingleQuoted("$X".into())]);
+    }
+
+    #[test]
+    fn special_parameters() {
+        assert_eq!(special_parameter("$"), Some(std::process::id().to_string()));
+        assert_eq!(special_parameter("#"), Some("0".into()));
+        assert_eq!(special_parameter("1"), Some("".into()));
+        assert_eq!(special_parameter("HOME"), None);
+
+        let pipeline = parse_pipeline("echo $? $# x$1").unwrap();
+        for word in pipeline.processes[0].words.iter() {
+            assert_eq!(word.expand().1, Vec::<String>::new());
+        }
+    }
+
+    #[test]
+    fn continuations() {
+        let pipeline = parse_pipeline("ls \\\n -l |\n wc").unwrap();
+        assert_eq!(args(&pipeline.processes[0]), vec!["ls", "-l"]);
+        assert_eq!(args(&pipeline.processes[1]), vec!["wc"]);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |src: &str| parse_pipeline(src).unwrap_err();
+
+        assert_eq!(error("echo 'abc"), ParseError::new(5, "unterminated single quote"));
+        assert_eq!(error("echo \"abc"), ParseError::new(5, "unterminated double quote"));
+        assert_eq!(error("echo $(ls)"), ParseError::new(5, "command substitution is not supported"));
+        assert_eq!(error("echo `ls`").pos, 5);
+        assert_eq!(error("a; b").pos, 1);
+        assert_eq!(error("a || b").pos, 2);
+        assert_eq!(error("a && b").pos, 2);
+        assert_eq!(error("a & b").msg, "expected end of pipeline after `&`");
+        assert_eq!(error("a |").msg, "expected command after `|`");
+        assert_eq!(error("| a").msg, "expected command");
+    }
+
+    #[test]
+    fn round_trip() {
+        for src in [
+            "echo 'a b' \"c d\" e\\ f",
+            "dc -e \"16 o 10 i @ p\" | wc -l",
+            "FOO='a b' env 2>&1 | sort &",
+            "echo \"it's\" 'say \"hi\"' \\$X a$X \"$HOME/x\"",
+            "printf '%s\\n' '' x",
+            "echo $$ \"$1x\" $?",
+        ] {
+            let first = parse_pipeline(src).unwrap();
+            let second = parse_pipeline(&sh(src)).unwrap();
+
+            assert_eq!(first.background, second.background, "{}", src);
+            assert_eq!(first.processes.len(), second.processes.len(), "{}", src);
+            for (a, b) in first.processes.iter().zip(second.processes.iter()) {
+                assert_eq!(args(a), args(b), "{}", src);
+                assert_eq!(a.assignments.len(), b.assignments.len(), "{}", src);
+                assert_eq!(a.redirects, b.redirects, "{}", src);
+            }
+
+            // the shell text is a fixed point
+            assert_eq!(sh(&sh(src)), sh(src), "{}", src);
+        }
+    }
+
+    #[test]
+    fn script() {
+        let src = "a\n# c\n\nb |\n  c\n| d\ne \\\n f\n";
+        assert_eq!(split_script(src), vec![
+            (1, "a".to_string()),
+            (4, "b |\n  c\n| d".to_string()),
+            (7, "e \\\n f".to_string())
+        ]);
+    }
+
+    #[test]
+    fn error_position() {
+        let src = "a |\n b 'x";
+        let err = parse_pipeline(src).unwrap_err();
+        assert_eq!(err.pos, 7);
+        assert_eq!(err.line_col(src), (1, 3));
+        assert_eq!(err.show(src), " b 'x\n   ^ unterminated single quote");
+    }
+}
@@ -1,12 +1,237 @@
 use {
     std::sync::{Arc, RwLock},
+    r3vi::{
+        view::{singleton::*, sequence::*},
+        projection::decorate_sequence::*
+    },
     laddertypes::{TypeTerm},
     nested::{
         type_system::{Context, MorphismTypePattern},
-        editors::list::*
+        editors::list::*,
+        terminal::{make_label, TerminalStyle, TerminalProjections},
+        tree::{NestedNode, TreeNav, TreeCursor}
     }
 };
 
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FileKind {
+    Directory,
+    File,
+    Symlink,
+    Unreadable,
+    Missing,
+}
+
+impl FileKind {
+    pub fn of(path: &std::path::Path) -> Self {
+        match std::fs::symlink_metadata(path) {
+            Ok(m) if m.file_type().is_symlink() => FileKind::Symlink,
+            Ok(m) if m.is_dir() => {
+                if std::fs::read_dir(path).is_ok() { FileKind::Directory } else { FileKind::Unreadable }
+            }
+            Ok(_) => {
+                if std::fs::File::open(path).is_ok() { FileKind::File } else { FileKind::Unreadable }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => FileKind::Unreadable,
+            Err(_) => FileKind::Missing
+        }
+    }
+
+    /// the kind named by the first rung of a type ladder,
+    /// e.g. `Directory~Path~<Seq Char>`
+    pub fn from_type(ladder: &str) -> Option<Self> {
+        match ladder.split('~').next()?.trim() {
+            "Directory" => Some(FileKind::Directory),
+            "File" => Some(FileKind::File),
+            _ => None
+        }
+    }
+
+    pub fn color(&self) -> (u8, u8, u8) {
+        match self {
+            FileKind::Directory => (80, 140, 220),
+            FileKind::File => (200, 200, 200),
+            FileKind::Symlink => (80, 200, 200),
+            FileKind::Unreadable => (220, 150, 50),
+            FileKind::Missing => (200, 80, 80),
+        }
+    }
+}
+
+/// the text of a `PathSegment` node
+pub fn segment_string(segment: &NestedNode) -> String {
+    let mut node = segment.clone();
+    node.goto(TreeCursor::none());
+
+    let segment_view = node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Char>"].into_iter());
+
+    let mut string = String::new();
+    for k in 0..segment_view.len().unwrap_or(0) {
+        let char_node = segment_view.get(&k).unwrap();
+        let char_view = char_node.get_data_view::<dyn SingletonView<Item = Option<char>>>(vec![].into_iter());
+        if let Some(c) = char_view.get() {
+            string.push(c);
+        }
+    }
+    string
+}
+
+/// the text of a `Path` node, every segment followed by `/`
+pub fn path_string(path: &NestedNode) -> String {
+    let segments = path.get_data_view::<dyn SequenceView<Item = NestedNode>>(
+        vec![
+            "<List PathSegment>"
+        ].into_iter()
+    ).unwrap();
+
+    let mut string = String::new();
+    for segment in segments.iter() {
+        string.push_str(&segment_string(&segment));
+        string.push('/');
+    }
+    string
+}
+
+/// `~` and `~/...` with `$HOME` put in, other paths as they are
+pub fn expand_home(path: &str) -> Option<String> {
+    if path == "~" || path.starts_with("~/") {
+        crate::env::get("HOME").map(|home| format!("{}{}", home, &path[1..]))
+    } else {
+        Some(path.to_string())
+    }
+}
+
+/// directory named by the given segments, relative ones are taken from `cwd`
+pub fn resolve_segments(cwd: &str, segments: &[String]) -> std::path::PathBuf {
+    let mut path = std::path::PathBuf::from(cwd);
+    for (i, segment) in segments.iter().enumerate() {
+        if i == 0 && segment.len() == 0 {
+            path = std::path::PathBuf::from("/");
+        } else if i == 0 && segment == "~" {
+            if let Some(home) = expand_home(segment) {
+                path = std::path::PathBuf::from(home);
+            }
+        } else {
+            path.push(segment);
+        }
+    }
+    path
+}
+
+/// entries of `dir` which start with `prefix`, sorted by name.
+/// hidden entries are only offered if the prefix starts with a dot.
+pub fn complete_segment(dir: &std::path::Path, prefix: &str) -> Vec<(String, FileKind)> {
+    let mut candidates = Vec::new();
+
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if let Ok(name) = entry.file_name().into_string() {
+                if name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.')) {
+                    let kind = match FileKind::of(&entry.path()) {
+                        // follow links to directories, so that they get completed like one
+                        FileKind::Symlink if entry.path().is_dir() => FileKind::Directory,
+                        kind => kind
+                    };
+                    candidates.push((name, kind));
+                }
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| a.0.cmp(&b.0));
+    candidates
+}
+
+/// longest prefix which all names share
+pub fn common_prefix<'a>(mut names: impl Iterator<Item = &'a str>) -> String {
+    let mut prefix = match names.next() {
+        Some(name) => name.to_string(),
+        None => { return String::new(); }
+    };
+
+    for name in names {
+        let len = prefix.chars().zip(name.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        prefix.truncate(len);
+    }
+    prefix
+}
+
+/// colours each segment by what it refers to in the filesystem.
+/// relative paths are looked up in `cwd`, without one they stay plain.
+/// a last segment which is not of the `expect`ed kind is flagged.
+pub fn highlight_segments(node: &mut NestedNode, cwd: Option<String>, expect: Option<FileKind>) {
+    let list = match node.get_edit::<ListEditor>() {
+        Some(list) => list,
+        None => { return; }
+    };
+
+    let seg_view = PTYListStyle::new( ("", "", "") ).get_seg_seq_view( &mut list.write().unwrap() );
+
+    node.view = Some(
+        seg_view
+            .enumerate()
+            .map(move |(n, segment)| {
+                let n = n.clone();
+                let list = list.clone();
+                let cwd = cwd.clone();
+
+                // (path, kind, whether it is of the expected kind) of the last lookup
+                let cache = Arc::new(RwLock::new(None::<(std::path::PathBuf, FileKind, bool)>));
+
+                segment.map_item(move |_, a| {
+                    let list = list.read().unwrap();
+                    if n >= list.data.len() {
+                        return *a;
+                    }
+
+                    let segments : Vec<String> = (0..=n).map(|i| segment_string(&list.data.get(i))).collect();
+                    let is_last = n + 1 == list.data.len();
+                    drop(list);
+
+                    let is_relative = segments[0].len() > 0 && segments[0] != "~";
+                    let path = match cwd.as_ref() {
+                        Some(cwd) => resolve_segments(cwd, &segments),
+                        None if !is_relative => resolve_segments("/", &segments),
+                        None => { return *a; }
+                    };
+
+                    let mut cache = cache.write().unwrap();
+                    let (kind, expected) = match cache.as_ref() {
+                        Some((cached_path, kind, expected)) if *cached_path == path => (*kind, *expected),
+                        _ => {
+                            let kind = FileKind::of(&path);
+                            // links count as what they point to
+                            let expected = match expect {
+                                Some(FileKind::Directory) => path.is_dir(),
+                                Some(FileKind::File) => path.is_file(),
+                                _ => true
+                            };
+                            *cache = Some((path, kind, expected));
+                            (kind, expected)
+                        }
+                    };
+
+                    if is_last && !expected {
+                        a.add_style_back(TerminalStyle::fg_color((200, 80, 80)))
+                            .add_style_back(TerminalStyle::bold(true))
+                    } else {
+                        a.add_style_back(TerminalStyle::fg_color(kind.color()))
+                    }
+                })
+            })
+            .separate(make_label("/"))
+            .to_grid_horizontal()
+            .flatten()
+    );
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
 pub fn init_ctx(ctx: &mut Context) {
     ctx.add_list_typename("PathSegment".into());
     ctx.add_morphism(
@@ -46,6 +271,7 @@ pub fn init_ctx(ctx: &mut Context) {
             |mut node, _dst_type:_| {
                 PTYListController::for_node( &mut node, Some('/'), None );
                 PTYListStyle::for_node( &mut node, ("","/","") );
+                highlight_segments( &mut node, None, None );
                 Some(node)
             }
         )
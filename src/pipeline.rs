@@ -7,6 +7,7 @@ use {
             port::UpdateTask
         },
         buffer::{
+            singleton::*,
             vec::*,
             index_hashmap::*
         }
@@ -24,25 +25,107 @@ use {
         editors::list::*,
         type_system::{Context, MorphismType, MorphismTypePattern}
     },
-    std::sync::Arc,
+    std::sync::{Arc, Mutex},
     std::sync::RwLock,
+    std::sync::atomic::{AtomicU64, Ordering},
+    std::time::Duration,
     std::io::{Read, Write},
 
     cgmath::{Point2, Vector2},
 
-    crate::process_types::ProcessTypes
+    crate::process_types::ProcessTypes,
+    crate::process::ProcessLauncher,
+    crate::reaper::ChildEvent
 };
 
 //<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
 
+/// stdin of the first stage while the pipeline is running.
+/// input is line buffered and echoed into the output box.
+struct PipelineInput {
+    stdin: std::process::ChildStdin,
+    echo: crate::stream::StreamWriter,
+    line: String,
+}
+
+impl PipelineInput {
+    fn push_char(&mut self, c: char) {
+        self.line.push(c);
+        self.echo.write(c.to_string().as_bytes());
+    }
+
+    fn pop_char(&mut self) {
+        if self.line.pop().is_some() {
+            self.echo.write(b"\x08 \x08");
+        }
+    }
+
+    fn flush_line(&mut self) -> std::io::Result<()> {
+        let line = std::mem::replace(&mut self.line, String::new());
+        self.stdin.write_all(line.as_bytes())?;
+        self.stdin.flush()
+    }
+
+    fn newline(&mut self) -> std::io::Result<()> {
+        self.line.push('\n');
+        self.echo.write(b"\n");
+        self.flush_line()
+    }
+}
+
+static SIGINT_TIMEOUT_MS: AtomicU64 = AtomicU64::new(2000);
+static SIGTERM_TIMEOUT_MS: AtomicU64 = AtomicU64::new(3000);
+
+/// how long `cancel()` waits after SIGINT and SIGTERM before escalating
+pub fn set_cancel_timeouts(sigint_timeout: Duration, sigterm_timeout: Duration) {
+    SIGINT_TIMEOUT_MS.store(sigint_timeout.as_millis() as u64, Ordering::Relaxed);
+    SIGTERM_TIMEOUT_MS.store(sigterm_timeout.as_millis() as u64, Ordering::Relaxed);
+}
+
+fn cancel_timeouts() -> (Duration, Duration) {
+    (
+        Duration::from_millis(SIGINT_TIMEOUT_MS.load(Ordering::Relaxed)),
+        Duration::from_millis(SIGTERM_TIMEOUT_MS.load(Ordering::Relaxed))
+    )
+}
+
+pub fn signal_name(sig: i32) -> &'static str {
+    match sig {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGTSTP => "SIGTSTP",
+        libc::SIGCONT => "SIGCONT",
+        _ => "?"
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
 pub struct PipelineLauncher {
     pub editor: NestedNode,
     pub cwd: Option<String>,
 
+    /// nesting depth of the `Pipeline` node, processes are one deeper
+    depth: OuterViewPort<dyn SingletonView<Item = usize>>,
+
     pub types: Arc<RwLock<ProcessTypes>>,
 
     _ptybox: Arc<RwLock<AsciiBox>>,
     pub box_port: ViewPort<dyn TerminalView>,
+
+    stderr_list: VecBuffer<OuterViewPort<dyn TerminalView>>,
+    stderr_boxes: Arc<RwLock<Vec<Arc<RwLock<AsciiBox>>>>>,
+    output_port: OuterViewPort<dyn TerminalView>,
     suspended: bool,
 
     pty_port: ViewPort<dyn TerminalView>,
@@ -52,6 +135,23 @@ pub struct PipelineLauncher {
 
     diag_buf: VecBuffer<nested::diagnostics::Message>,
 
+    input: Arc<RwLock<Option<PipelineInput>>>,
+
+    /// a single command which runs in a PTY, and its index
+    process: Option<(usize, Arc<RwLock<ProcessLauncher>>)>,
+
+    /// spawned stages and their index in the pipeline,
+    /// stages without words are skipped
+    children: Vec<(usize, Arc<Mutex<std::process::Child>>)>,
+    pgid: Option<i32>,
+    job_id: Option<usize>,
+
+    /// success of each stage, `None` while it is running
+    stage_status: Arc<RwLock<Vec<Option<bool>>>>,
+
+    status_grid: IndexBuffer<Point2<i16>, OuterViewPort<dyn TerminalView>>,
+    status_port: OuterViewPort<dyn TerminalView>,
+
     comp_port: ViewPort<dyn TerminalView>,
     _compositor: Arc<RwLock<nested::terminal::TerminalCompositor>>,
 }
@@ -70,7 +170,6 @@ impl PipelineLauncher {
                     PTYListController::for_node( &mut node, Some('|'), None );
                     PTYListStyle::for_node( &mut node, (""," | ","") );
 
-                    /*
                     let pipeline_launcher = crate::pipeline::PipelineLauncher::new(node.clone());
 
                     node.view = Some(pipeline_launcher.editor_view());
@@ -82,8 +181,7 @@ impl PipelineLauncher {
                     node.cmd.set(Some(editor.clone() as Arc<RwLock<dyn ObjCommander + Send + Sync>>));
                     node.editor.set(Some(editor.clone() as Arc<dyn std::any::Any + Send + Sync>));
 
-                    */
-                    
+
                     Some(node)                
                 }
             )
@@ -91,14 +189,17 @@ impl PipelineLauncher {
         ctx.add_node_ctor(
             "Pipeline",
             Arc::new(
-                |ctx: Arc<RwLock<Context>>, dst_typ: TypeTerm, depth| {
+                |ctx: Arc<RwLock<Context>>, dst_typ: TypeTerm, depth: OuterViewPort<dyn SingletonView<Item = usize>>| {
                     let mut node = Context::make_node(
                         &ctx,
                         Context::parse(&ctx, "<List Process>"),
-                        depth
+                        depth.clone()
                     ).unwrap();
 
                     node = node.morph(dst_typ);
+                    if let Some(launcher) = node.get_edit::<PipelineLauncher>() {
+                        launcher.write().unwrap().depth = depth;
+                    }
 
                     Some(node)
                 }
@@ -119,6 +220,16 @@ impl PipelineLauncher {
 
         let mut diag_buf = VecBuffer::new();
 
+        let mut status_grid = IndexBuffer::new();
+        let status_port = status_grid.get_port().flatten();
+        status_grid.insert(Point2::new(0, 0), make_label(" "));
+
+        let stderr_list = VecBuffer::new();
+        let mut output_list = VecBuffer::new();
+        output_list.push(box_port.outer());
+        output_list.push(stderr_list.get_port().to_sequence().to_grid_vertical().flatten());
+        let output_port = output_list.get_port().to_sequence().to_grid_vertical().flatten();
+
         compositor.write().unwrap().push(
             box_port
                 .outer()
@@ -144,14 +255,27 @@ impl PipelineLauncher {
             suspended: false,
             comp_port,
             box_port,
+            stderr_list,
+            stderr_boxes: Arc::new(RwLock::new(Vec::new())),
+            output_port,
             cwd: None,
+            depth: SingletonBuffer::new(0).get_port(),
             _compositor: compositor,
 
             typegrid,
             types: Arc::new(RwLock::new(ProcessTypes::new(ctx))),
             typeinfo_port,
 
-            diag_buf
+            diag_buf,
+            input: Arc::new(RwLock::new(None)),
+            process: None,
+            children: Vec::new(),
+            pgid: None,
+            job_id: None,
+            stage_status: Arc::new(RwLock::new(Vec::new())),
+
+            status_grid,
+            status_port
         }
     }
 
@@ -159,6 +283,16 @@ impl PipelineLauncher {
         self.comp_port.outer()
     }
 
+    /// success/failure indicator of the last run
+    pub fn status_view(&self) -> OuterViewPort<dyn TerminalView> {
+        self.status_port.clone()
+    }
+
+    /// output box followed by the stderr boxes of all stages
+    pub fn output_view(&self) -> OuterViewPort<dyn TerminalView> {
+        self.output_port.clone()
+    }
+
     pub fn editor_view(&self) -> OuterViewPort<dyn TerminalView> {
         self.editor.get_view()
     }
@@ -167,47 +301,159 @@ impl PipelineLauncher {
         self.typeinfo_port.clone()
     }
 
-    pub fn get_strings(&self) -> Vec<Vec<String>> {
+    /// the edited pipeline as words, before expansion
+    pub fn get_pipeline(&self) -> crate::parse::Pipeline {
         let ctx = self.editor.ctx.clone();
 
-        let mut pipeline_strings = Vec::<Vec<String>>::new();
-
         let pipeline_view = self.editor.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Process>"].into_iter());
 
-        for i in 0..pipeline_view.len().unwrap_or(0) {
-            let process_node = pipeline_view.get(&i).unwrap();
-            let mut process_strings = Vec::<String>::new();
+        crate::parse::Pipeline::from_words(
+            (0..pipeline_view.len().unwrap_or(0))
+                .filter_map(|i| pipeline_view.get(&i))
+                .map(|process_node| {
+                    let process_view = process_node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List ProcessArg>"].into_iter());
+                    (0..process_view.len().unwrap_or(0))
+                        .filter_map(|j| process_view.get(&j))
+                        .map(|arg_node| crate::arg::arg_word(&ctx, &arg_node))
+                        .collect()
+                })
+                .collect()
+        )
+    }
 
+    /// argv of every process, with variables substituted
+    pub fn get_strings(&self) -> Vec<Vec<String>> {
+        self.get_pipeline().get_strings()
+    }
+
+    /// whether the cursor is inside a quoted part of an argument
+    pub fn in_quote(&self) -> bool {
+        match self.editor.get_edit::<ListEditor>() {
+            Some(list) => crate::arg::cursor_in_quote(&self.editor.ctx, &list.read().unwrap()),
+            None => false
+        }
+    }
+
+    /// arguments of the `j`-th process, empty ones included
+    pub fn process_words(&self, j: usize) -> Vec<crate::parse::Word> {
+        let pipeline_view = self.editor.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Process>"].into_iter());
+
+        if let Some(process_node) = pipeline_view.get(&j) {
             let process_view = process_node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List ProcessArg>"].into_iter());
 
-            for j in 0..process_view.len().unwrap_or(0) {
-                let arg_node = process_view.get(&j).unwrap();
-                let arg_view = arg_node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Char>"].into_iter());
-                
-                let mut arg = String::new();
-                for k in 0..arg_view.len().unwrap_or(0) {
-                    let char_node = arg_view.get(&k).unwrap();
-                    let char_view = char_node.get_data_view::<dyn SingletonView<Item = Option<char>>>(vec![].into_iter());
-                    if let Some(c) = char_view.get() {
-                        arg.push(c);
-                    }
-                }
+            (0..process_view.len().unwrap_or(0))
+                .filter_map(|i| process_view.get(&i))
+                .map(|arg_node| crate::arg::arg_word(&self.editor.ctx, &arg_node))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
 
-                if arg.len() > 0 {
-                    process_strings.push(arg);
-                }
+    /// the index and launcher of the only process with arguments
+    fn single_process(&self) -> Option<(usize, Arc<RwLock<ProcessLauncher>>)> {
+        let pipeline_view = self.editor.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Process>"].into_iter());
+
+        let mut nodes = (0..pipeline_view.len().unwrap_or(0))
+            .filter(|j| crate::parse::Process::from_words(self.process_words(*j)).words.len() > 0)
+            .filter_map(|j| Some((j, pipeline_view.get(&j)?)));
+
+        match (nodes.next(), nodes.next()) {
+            (Some((j, node)), None) => Some((j, crate::process::launcher(&node)?)),
+            _ => None
+        }
+    }
+
+    /// the pipeline as quoted POSIX sh text
+    pub fn to_sh(&self) -> String {
+        crate::export::pipeline_to_sh(&self.get_pipeline())
+    }
+
+    pub fn copy_sh(&mut self) {
+        let text = self.to_sh();
+        match crate::export::copy_to_clipboard(&text) {
+            Ok(()) => {
+                self.diag_buf.push(nested::diagnostics::make_info(
+                    make_label(&format!("copied: {}", text))
+                ));
             }
+            Err(err) => {
+                self.diag_buf.push(nested::diagnostics::make_error(
+                    make_label(&format!("could not copy: {}", err))
+                ));
+            }
+        }
+    }
 
-            if process_strings.len() > 0 {
-                pipeline_strings.push(process_strings);
+    /// replaces the edited pipeline by the parsed shell string,
+    /// a syntax error is also shown as diagnostic
+    pub fn load_str(&mut self, s: &str) -> Result<(), crate::parse::ParseError> {
+        let pipeline = match crate::parse::parse_pipeline(s) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                self.diag_buf.push(nested::diagnostics::make_error(
+                    make_label(&format!("{}: {}", s.trim(), err))
+                ));
+                return Err(err);
             }
+        };
+        crate::parse::load_pipeline(&self.editor.ctx, &self.editor, &pipeline, self.depth.clone());
+        self.pty_reset();
+        Ok(())
+    }
+
+    /// resolves the command name of every process against builtins, aliases and `$PATH`
+    fn check_executables(&mut self, strings: &Vec<Vec<String>>) -> bool {
+        let mut ok = true;
+        let n_stages = strings.iter().filter(|argv| argv.len() > 0).count();
+
+        for (j, argv) in strings.iter().enumerate() {
+            let name = match argv.first() {
+                Some(name) => name,
+                None => { continue; }
+            };
+
+            let msg = match crate::which::check(name, self.cwd.as_deref()) {
+                // builtins run inside the shell, which has no pipe to connect
+                Ok(crate::which::Resolution::Builtin) if n_stages > 1 =>
+                    format!("{}: builtins can't run in a pipeline", name),
+                Ok(_) => { continue; }
+                Err(msg) => msg
+            };
+
+            self.diag_buf.push({
+                let mut msg = nested::diagnostics::make_error(make_label(&msg));
+                msg.addr.push(j);
+                msg
+            });
+            ok = false;
         }
 
-        pipeline_strings
+        ok
+    }
+
+    /// warns about variables which are not set
+    fn check_variables(&mut self) {
+        for (j, process) in self.get_pipeline().processes.iter().enumerate() {
+            let words = process.assignments.iter().map(|(_, value)| value).chain(process.words.iter());
+            for word in words {
+                for name in word.expand().1 {
+                    self.diag_buf.push({
+                        let mut msg = nested::diagnostics::make_warn(
+                            make_label(&format!("${} is not set", name))
+                        );
+                        msg.addr.push(j);
+                        msg
+                    });
+                }
+            }
+        }
     }
 
     pub fn typecheck(&mut self) -> bool {
-        let strings = self.get_strings();
+        let mut pipeline = self.get_pipeline();
+        pipeline.expand_aliases();
+        let strings = pipeline.get_strings();
 
         if strings.len() == 0 {
             self.diag_buf.push(nested::diagnostics::make_warn(
@@ -216,21 +462,22 @@ impl PipelineLauncher {
             return false;
         }
 
-        let ctx = self.editor.ctx.clone();
+        self.check_variables();
 
-        let types = self.types.read().unwrap();
-        let mut last_stdout_type : Option<TypeTerm> = None;
+        if !self.check_executables(&strings) {
+            return false;
+        }
+
+        let ctx = self.editor.ctx.clone();
 
         let types = self.types.read().unwrap();
         let mut last_stdout_type : Option<TypeTerm> = None;
 
-        let mut typestack = vec![];
-
         for (j, process_str) in strings.iter().enumerate() {
             if process_str.len() > 0 {
                 if let (Some(last_stdout), Some(expected)) = (last_stdout_type, types.get_stdin_type( &process_str )) {
 
-                    // todo
+                    // `x` is the rung of the stdout ladder at which the expected type begins
                     match last_stdout.is_syntactic_subtype_of(&expected) {
                         Ok(x) => {
                             let mut grid = IndexBuffer::new();
@@ -241,10 +488,6 @@ impl PipelineLauncher {
                             let expected_lnf = expected.clone().get_lnf_vec();
 
                             for (i,t) in last_stdout_lnf.iter().enumerate() {
-                                if i < x {
-                                    typestack.push(t.clone());
-                                }
-
                                 let tstr = ctx.read().unwrap().type_term_to_str( t );
                                 grid.insert(Point2::new(0, 2+i as i16), make_label(&tstr).with_fg_color(
                                     if i < x {
@@ -272,14 +515,6 @@ impl PipelineLauncher {
                             });
                         }
                         Err((first_match, first_mismatch)) => {
-/*
-                            let (first_match, first_mismatch) = match x {
-                                Some((first_match, first_mismatch)) => {
-                                    (Some(first_match), Some(first_mismatch))
-                                }
-                                None => (None, None)
-                            };
-*/
                             let mut grid = IndexBuffer::new();
                             grid.insert(Point2::new(0 as i16, 0 as i16), make_label("type error. ").with_style(TerminalStyle::bold(true)));
                             grid.insert(Point2::new(0 as i16, 1 as i16), make_label("found").with_style(TerminalStyle::bold(true)));
@@ -324,13 +559,6 @@ impl PipelineLauncher {
                 }
 
                 last_stdout_type = types.get_stdout_type( &process_str );
-                /*
-                if let Some(mut last_stdout_type) = last_stdout_type.as_mut() {
-                    while let Some(x) = typestack.pop() {
-                        last_stdout_type.0.insert(0, x);   
-                    }
-            }
-                */
             }
         }
 
@@ -344,77 +572,470 @@ impl PipelineLauncher {
     pub fn launch(&mut self) {
         self.pty_reset();
 
-        if self.typecheck()
-        {
-            let strings = self.get_strings();
+        let mut pipeline = self.get_pipeline();
+        if pipeline.processes.len() > 0 {
+            crate::builtin::add_history(self.to_sh());
+        }
 
-            let mut execs = Vec::new();
-            for process_str in strings {
-                if process_str.len() > 0 {                 
-                    let mut exec = subprocess::Exec::cmd(process_str[0].clone());
+        pipeline.expand_aliases();
+        let background = pipeline.background;
+        let strings = pipeline.get_strings();
 
-                    if let Some(cwd) = self.cwd.as_ref() {
-                        exec = exec.cwd(cwd);
-                    }
+        if strings.len() == 1 {
+            if let Some(builtin) = strings[0].first().and_then(|name| crate::builtin::get(name)) {
+                self.run_builtin(&*builtin, &strings[0][1..]);
+                return;
+            }
+        }
 
-                    for i in 1..process_str.len() {
-                        exec = exec.arg(process_str[i].clone());
-                    }
-                    execs.push(exec);
+        if self.typecheck()
+        {
+            // a single command gets a terminal of its own
+            if !background && pipeline.processes.len() == 1 {
+                if let Some((j, process)) = self.single_process() {
+                    self.launch_pty(j, process);
+                    return;
                 }
             }
 
-            if execs.len() > 1 {
-                let pipeline = subprocess::Pipeline::from_exec_iter(execs);
+            let cmdline = crate::export::pipeline_to_sh(&pipeline);
 
-                match pipeline.stream_stdout() {
-                    Ok(mut stdout) => {
-                        let max_size = cgmath::Vector2::new(80, 40);
+            match self.spawn(&pipeline) {
+                Ok(Some(stdout)) => {
+                    let (output, mut output_reader) = crate::stream::stream();
+                    let max_size = crate::layout::output_size();
+                    let port = self.pty_port.inner();
 
-                        let port = self.pty_port.inner();
+                    async_std::task::spawn_blocking(move || {
+                        nested::terminal::ansi_parser::read_ansi_from(&mut output_reader, max_size, port);
+                    });
+
+                    // background jobs read EOF
+                    let stdin = self.children.first()
+                        .and_then(|(_, child)| child.lock().unwrap().stdin.take())
+                        .filter(|_| !background);
+
+                    self.input = Arc::new(RwLock::new(
+                        stdin.map(|stdin| PipelineInput {
+                            stdin,
+                            echo: output.clone(),
+                            line: String::new()
+                        })
+                    ));
+
+                    async_std::task::spawn_blocking(move || {
+                        output.copy_from(stdout);
+                    });
 
-                        async_std::task::spawn_blocking(move || {
-                            nested::terminal::ansi_parser::read_ansi_from(&mut stdout, max_size, port);
-                        });
+                    let job_id = crate::job::add(self.pgid.unwrap(), cmdline, !background);
+                    self.job_id = Some(job_id);
+                    if background {
+                        self.diag_buf.push(nested::diagnostics::make_info(
+                            make_label(&format!("[{}] {}", job_id, self.pgid.unwrap()))
+                        ));
                     }
 
-                    Err(err) => {
-                        self.diag_buf.push(
+                    self.wait_children();
+                }
+
+                Ok(None) => {}
+
+                Err(err) => {
+                    self.diag_buf.push(
                         nested::diagnostics::make_error(
                             make_label(
-                                &format!("error spawning pipeline: {:?}", err)
+                                &format!("error spawning pipeline: {}", err)
                             )
                         ));
+                }
+            }
+        }
+    }
+
+    /// runs `process` in a PTY which is shown in the output box
+    fn launch_pty(&mut self, j: usize, process: Arc<RwLock<ProcessLauncher>>) {
+        {
+            let mut p = process.write().unwrap();
+            p.cwd = self.cwd.clone();
+            p.launch_pty(&self.pty_port);
+        }
+        self.process = Some((j, process));
+        self.relay_process_diags();
+    }
+
+    /// job of the last launch, that of the PTY for a single command
+    pub fn job_id(&self) -> Option<usize> {
+        self.job_id.or_else(|| {
+            self.process.as_ref().and_then(|(_, process)| process.read().unwrap().job_id())
+        })
+    }
+
+    /// shows the diagnostics of the PTY process with the pipeline's
+    fn relay_process_diags(&mut self) {
+        if let Some((j, process)) = self.process.as_ref() {
+            for mut msg in process.write().unwrap().take_diags() {
+                msg.addr.push(*j);
+                self.diag_buf.push(msg);
+            }
+        }
+    }
+
+    /// true while a PTY in the output box takes all keys
+    pub fn is_captured(&self) -> bool {
+        self.process.as_ref()
+            .map(|(_, process)| process.read().unwrap().is_captured())
+            .unwrap_or(false)
+    }
+
+    /// prints text into the output box
+    fn write_output(&self, text: String) {
+        let max_size = crate::layout::output_size();
+        let port = self.pty_port.inner();
+
+        async_std::task::spawn_blocking(move || {
+            nested::terminal::ansi_parser::read_ansi_from(&mut text.as_bytes(), max_size, port);
+        });
+    }
+
+    /// runs a builtin in-process, its output goes into the output box
+    pub fn run_builtin(&mut self, builtin: &dyn crate::builtin::Builtin, args: &[String]) {
+        let mut sh = crate::builtin::Shell::new(self.cwd.clone());
+        let result = builtin.run(args, &mut sh);
+
+        self.cwd = Some(sh.cwd);
+        self.write_output(sh.stdout);
+
+        match result {
+            Ok(()) => {
+                self.status_grid.insert(Point2::new(0, 0), make_label("✔").with_fg_color((50, 200, 50)));
+            }
+            Err(err) => {
+                self.status_grid.insert(Point2::new(0, 0), make_label("✘").with_fg_color((200, 50, 50)));
+                self.diag_buf.push(nested::diagnostics::make_error(
+                    make_label(&format!("{}: {}", builtin.name(), err))
+                ));
+            }
+        }
+    }
+
+    /// spawns all stages into one new process group and
+    /// returns the read end of the last stage's stdout
+    fn spawn(&mut self, pipeline: &crate::parse::Pipeline) -> std::io::Result<Option<std::fs::File>> {
+        use std::os::unix::process::CommandExt;
+
+        self.children.clear();
+        self.pgid = None;
+
+        let mut last_stdout : Option<std::fs::File> = None;
+
+        for (j, process) in pipeline.processes.iter().enumerate() {
+            let merge_stderr = process.merges_stderr();
+            let (vars, argv) = process.expand();
+            if argv.len() == 0 {
+                continue;
+            }
+
+            let mut cmd = std::process::Command::new(&argv[0]);
+            cmd.args(&argv[1..]);
+            cmd.env_clear();
+            cmd.envs(crate::env::vars());
+            cmd.envs(vars);
+
+            if let Some(cwd) = self.cwd.as_ref() {
+                cmd.current_dir(cwd);
+            }
+
+            cmd.process_group(self.pgid.unwrap_or(0));
+
+            cmd.stdin(match last_stdout.take() {
+                Some(prev) => std::process::Stdio::from(prev),
+                None => std::process::Stdio::piped()
+            });
+
+            let (out_read, out_write) = subprocess::make_pipe()?;
+            cmd.stdout(out_write.try_clone()?);
+            if merge_stderr {
+                cmd.stderr(out_write);
+            } else {
+                drop(out_write);
+                cmd.stderr(std::process::Stdio::piped());
+            }
+
+            let mut child = match cmd.spawn() {
+                Ok(child) => child,
+                Err(err) => {
+                    // take down the stages that already run
+                    self.signal(libc::SIGKILL);
+                    return Err(err);
+                }
+            };
+            // the pipe ends held by `cmd` are closed here
+            drop(cmd);
+
+            if self.pgid.is_none() {
+                self.pgid = Some(child.id() as i32);
+            }
+
+            if let Some(stderr) = child.stderr.take() {
+                self.capture_stderr(j, stderr);
+            }
+
+            self.children.push((j, Arc::new(Mutex::new(child))));
+            last_stdout = Some(out_read);
+        }
+
+        Ok(last_stdout)
+    }
+
+    /// sends a signal to the process group of the pipeline
+    fn signal(&self, sig: i32) {
+        if let Some(pgid) = self.pgid {
+            unsafe { libc::kill(-pgid, sig); }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stage_status.read().unwrap().iter().any(|s| s.is_none())
+    }
+
+    /// interrupts the running pipeline, escalating to SIGTERM
+    /// and SIGKILL if it does not exit within the cancel timeouts
+    pub fn cancel(&mut self) {
+        let pgid = match self.pgid {
+            Some(pgid) if self.is_running() => pgid,
+            _ => { return; }
+        };
+
+        let stage_status = self.stage_status.clone();
+        let mut diag_buf = self.diag_buf.clone();
+
+        async_std::task::spawn_blocking(move || {
+            let is_done = || stage_status.read().unwrap().iter().all(|s| s.is_some());
+            let wait_done = |timeout: Duration| {
+                let start = std::time::Instant::now();
+                while start.elapsed() < timeout {
+                    if is_done() {
+                        return true;
                     }
+                    std::thread::sleep(Duration::from_millis(10));
                 }
-            } else if execs.len() == 1 {
-                let e = execs[0].clone();
-                match e.stream_stdout() {
-                    Ok(mut stdout) => {
-                        let max_size = cgmath::Vector2::new(80, 40);
+                is_done()
+            };
+
+            let (sigint_timeout, sigterm_timeout) = cancel_timeouts();
+
+            for (sig, timeout) in vec![
+                (libc::SIGINT, Some(sigint_timeout)),
+                (libc::SIGTERM, Some(sigterm_timeout)),
+                (libc::SIGKILL, None)
+            ] {
+                unsafe { libc::kill(-pgid, sig); }
+
+                if timeout.map(|t| wait_done(t)).unwrap_or(true) {
+                    diag_buf.push(
+                        if sig == libc::SIGKILL {
+                            nested::diagnostics::make_warn(make_label("pipeline killed (SIGKILL)"))
+                        } else {
+                            nested::diagnostics::make_info(make_label(
+                                &format!("pipeline stopped by {}", signal_name(sig))
+                            ))
+                        }
+                    );
+                    break;
+                }
+            }
+        });
+    }
 
-                        let port = self.pty_port.inner();
+    /// waits on every stage in the background and publishes
+    /// its exit status as diagnostic addressed to that stage
+    fn wait_children(&mut self) {
+        let stage_status = Arc::new(RwLock::new(vec![ None; self.children.len() ]));
+        self.stage_status = stage_status.clone();
+        self.status_grid.insert(Point2::new(0, 0), make_label("…").with_fg_color((160, 160, 20)));
+
+        for (k, (j, child)) in self.children.iter().enumerate() {
+            let j = *j;
+            let stage_status = stage_status.clone();
+            let mut diag_buf = self.diag_buf.clone();
+            let mut status_grid = self.status_grid.clone();
+            let input = self.input.clone();
+
+            let pid = child.lock().unwrap().id() as i32;
+            let job_id = self.job_id;
+
+            crate::reaper::watch(pid, move |event| {
+                match event {
+                    ChildEvent::Stopped(_) => {
+                        if let Some(job_id) = job_id {
+                            crate::job::set_state(job_id, crate::job::JobState::Stopped);
+                        }
+                        status_grid.insert(Point2::new(0, 0), make_label("⏸").with_fg_color((160, 160, 20)));
+                        return;
+                    }
+                    ChildEvent::Continued => {
+                        if let Some(job_id) = job_id {
+                            if let Some(job) = crate::job::get(job_id) {
+                                if job.state == crate::job::JobState::Stopped {
+                                    crate::job::set_state(job_id, crate::job::JobState::Running);
+                                }
+                            }
+                        }
+                        status_grid.insert(Point2::new(0, 0), make_label("…").with_fg_color((160, 160, 20)));
+                        return;
+                    }
+                    _ => {}
+                }
 
-                        async_std::task::spawn_blocking(move || {
-                            nested::terminal::ansi_parser::read_ansi_from(&mut stdout, max_size, port);
-                        });
+                diag_buf.push({
+                    let mut msg = match event {
+                        ChildEvent::Exited(0) =>
+                            nested::diagnostics::make_info(make_label("exit 0")),
+                        ChildEvent::Exited(code) =>
+                            nested::diagnostics::make_error(make_label(&format!("exit {}", code))),
+                        ChildEvent::Signaled(sig) =>
+                            nested::diagnostics::make_error(make_label(
+                                &format!("killed by signal {} ({})", sig, signal_name(sig))
+                            )),
+                        _ =>
+                            nested::diagnostics::make_warn(make_label("exit status unknown"))
+                    };
+                    msg.addr.push(j);
+                    msg
+                });
+
+                let mut stage_status = stage_status.write().unwrap();
+                stage_status[k] = Some(event.success());
+
+                if stage_status.iter().all(|s| s.is_some()) {
+                    *input.write().unwrap() = None;
+                    if let Some(job_id) = job_id {
+                        crate::job::set_state(job_id, crate::job::JobState::Done);
                     }
-                    Err(err) => {
-                        self.diag_buf.push(
-                            nested::diagnostics::make_error(
-                                make_label(
-                                    &format!("error spawning pipeline: {:?}", err)
-                                )
-                            ));
+
+                    if stage_status.iter().all(|s| *s == Some(true)) {
+                        status_grid.insert(Point2::new(0, 0), make_label("✔").with_fg_color((50, 200, 50)));
+                    } else {
+                        status_grid.insert(Point2::new(0, 0), make_label("✘").with_fg_color((200, 50, 50)));
                     }
                 }
+            });
+        }
+    }
+
+    /// shows the stderr of stage `j` in its own box once it writes anything
+    fn capture_stderr(&self, j: usize, mut stderr: impl Read + Send + 'static) {
+        let mut stderr_list = self.stderr_list.clone();
+        let stderr_boxes = self.stderr_boxes.clone();
+
+        async_std::task::spawn_blocking(move || {
+            let mut buf = [0; 4096];
+            let n = match stderr.read(&mut buf) {
+                Ok(0) | Err(_) => { return; }
+                Ok(n) => n
+            };
+
+            let (output, mut output_reader) = crate::stream::stream();
+            output.write(&buf[..n]);
+
+            let err_port = ViewPort::<dyn TerminalView>::new();
+            let err_box_port = ViewPort::<dyn TerminalView>::new();
+
+            let err_box = AsciiBox::new(
+                cgmath::Vector2::new(0, 0),
+                err_port.outer().map_item(|_, a: &TerminalAtom| {
+                    a.add_style_back(TerminalStyle::fg_color((230, 150, 150)))
+                }),
+                err_box_port.inner(),
+            );
+            stderr_boxes.write().unwrap().push(err_box);
+
+            let mut grid = IndexBuffer::new();
+            grid.insert_iter(vec![
+                (Point2::new(0, 0), make_label(&format!("[{}] stderr", j)).with_fg_color((200, 80, 80))),
+                (Point2::new(0, 1), err_box_port.outer()
+                 .map_item(|_, a| a.add_style_back(TerminalStyle::fg_color((150, 40, 40)))))
+            ]);
+            stderr_list.push(grid.get_port().flatten());
+
+            let max_size = crate::layout::output_size();
+            let port = err_port.inner();
+            async_std::task::spawn_blocking(move || {
+                nested::terminal::ansi_parser::read_ansi_from(&mut output_reader, max_size, port);
+            });
+
+            output.copy_from(stderr);
+        });
+    }
+
+    /// true while keystrokes are forwarded to the stdin of the first stage
+    pub fn accepts_input(&self) -> bool {
+        self.input.read().unwrap().is_some() &&
+            self.job_id.and_then(crate::job::get)
+                .map(|job| job.foreground && job.state == crate::job::JobState::Running)
+                .unwrap_or(false)
+    }
+
+    /// stops the pipeline (SIGTSTP), it can be continued with `fg` or `bg`
+    pub fn suspend(&mut self) {
+        if let Some(job_id) = self.job_id {
+            if self.is_running() {
+                crate::job::suspend(job_id);
+            }
+        }
+    }
+
+    /// closes stdin of the first stage
+    pub fn send_eof(&mut self) {
+        let mut input = self.input.write().unwrap();
+        if let Some(i) = input.as_mut() {
+            i.flush_line().ok();
+        }
+        *input = None;
+    }
+
+    fn handle_input(&mut self, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+        let co = cmd_obj.read().unwrap();
+
+        let event = co.get_view::<dyn SingletonView<Item = TerminalEvent>>().map(|v| v.get());
+        if let Some(TerminalEvent::Input(Event::Key(Key::Ctrl('d')))) = event {
+            self.send_eof();
+            return TreeNavResult::Continue;
+        }
+
+        let c = co.get_view::<dyn SingletonView<Item = char>>().map(|v| v.get());
+        let list_cmd = co.get_view::<dyn SingletonView<Item = ListCmd>>().map(|v| v.get());
+
+        let mut input = self.input.write().unwrap();
+        if let Some(i) = input.as_mut() {
+            let result = match (c, event, list_cmd) {
+                (Some('\n'), _, _) |
+                (_, Some(TerminalEvent::Input(Event::Key(Key::Char('\n')))), _) => i.newline(),
+
+                (Some(c), _, _) |
+                (_, Some(TerminalEvent::Input(Event::Key(Key::Char(c)))), _) => { i.push_char(c); Ok(()) }
+
+                (_, Some(TerminalEvent::Input(Event::Key(Key::Backspace))), _) |
+                (_, _, Some(ListCmd::DeletePxev)) => { i.pop_char(); Ok(()) }
+
+                _ => Ok(())
+            };
+
+            if result.is_err() {
+                // broken pipe, the first stage does not read anymore
+                *input = None;
             }
         }
+
+        TreeNavResult::Continue
     }
 
     pub fn pty_reset(&mut self) {
+        self.process = None;
         self.diag_buf.clear();
         self.typegrid.clear();
+        self.stderr_list.clear();
+        self.stderr_boxes.write().unwrap().clear();
         let mut empty = IndexBuffer::new();
         self.pty_port.set_view(empty.get_port().get_view());
     }
@@ -422,9 +1043,21 @@ impl PipelineLauncher {
 
 use nested::type_system::ReprTree;
 use nested::commander::ObjCommander;
+use nested::terminal::TerminalEvent;
+use termion::event::{Event, Key};
 
 impl ObjCommander for PipelineLauncher {
     fn send_cmd_obj(&mut self, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+        if self.is_captured() {
+            let (_, process) = self.process.clone().unwrap();
+            let result = process.write().unwrap().send_cmd_obj(cmd_obj);
+            self.relay_process_diags();
+            return result;
+        }
+
+        if self.accepts_input() {
+            return self.handle_input(cmd_obj);
+        }
 
         let ctx = self.editor.ctx.clone();
         let ctx = ctx.read().unwrap();
@@ -441,7 +1074,14 @@ impl ObjCommander for PipelineLauncher {
 
                 if c == '\n' {
                     self.launch();
-                    TreeNavResult::Exit
+                    if self.is_captured() {
+                        TreeNavResult::Continue
+                    } else {
+                        TreeNavResult::Exit
+                    }
+                } else if c == '|' && self.in_quote() {
+                    let list = self.editor.get_edit::<ListEditor>().unwrap();
+                    crate::arg::send_to_item(&list, cmd_obj)
                 } else {
                     self.editor.send_cmd_obj(cmd_obj)
                 }
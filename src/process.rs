@@ -1,11 +1,16 @@
 use {
     crate::pty::{PTYStatus, PTY},
+    crate::reaper::ChildEvent,
+    crate::parse::Word,
     r3vi::{
         view::{
             OuterViewPort, ViewPort,
             singleton::*,
             sequence::*,
-        }
+            index::*,
+        },
+        buffer::vec::*,
+        projection::decorate_sequence::*
     },
     laddertypes::{TypeTerm},
     nested::{
@@ -13,12 +18,14 @@ use {
             list::{ListCursorMode, ListEditor, PTYListController, PTYListStyle}
         },
         terminal::{
+            make_label,
             TerminalAtom, TerminalEditor, TerminalEvent, TerminalStyle,
             TerminalView,
             widgets::ascii_box::AsciiBox
         },
         tree::{TreeCursor, TreeNav, NestedNode, TreeNavResult},
-        type_system::{Context, MorphismTypePattern}
+        type_system::{Context, MorphismTypePattern, ReprTree},
+        commander::ObjCommander
     },
     std::sync::Arc,
     std::sync::RwLock,
@@ -29,7 +36,17 @@ use {
 
 pub struct ProcessLauncher {
     cmd_editor: NestedNode,
+
+    /// arguments of the process
+    list: Arc<RwLock<ListEditor>>,
+
+    /// controller of the node before the launcher was installed
+    inner: Option<Arc<RwLock<dyn ObjCommander + Send + Sync>>>,
+
+    pub cwd: Option<String>,
+    pub diag_buf: VecBuffer<nested::diagnostics::Message>,
     pty: Option<crate::pty::PTY>,
+    job_id: Option<usize>,
     _ptybox: Arc<RwLock<AsciiBox>>,
     suspended: bool,
 
@@ -40,6 +57,82 @@ pub struct ProcessLauncher {
     _compositor: Arc<RwLock<nested::terminal::TerminalCompositor>>,
 }
 
+/// the launcher which is installed in a `Process` node
+pub fn launcher(node: &NestedNode) -> Option<Arc<RwLock<ProcessLauncher>>> {
+    node.get_edit::<ProcessLauncher>()
+}
+
+/// the list editor of a node, the arguments for a `Process` node
+pub fn arg_list(node: &NestedNode) -> Option<Arc<RwLock<ListEditor>>> {
+    match launcher(node) {
+        Some(launcher) => Some(launcher.read().unwrap().list.clone()),
+        None => node.get_edit::<ListEditor>()
+    }
+}
+
+/// colours the command name of a `Process` node by whether it resolves
+fn highlight_command_name(node: &mut NestedNode) {
+    let ctx = node.ctx.clone();
+    let list = match node.get_edit::<ListEditor>() {
+        Some(list) => list,
+        None => { return; }
+    };
+
+    let seg_view = PTYListStyle::new( ("", "", "") ).get_seg_seq_view( &mut list.write().unwrap() );
+
+    // (command name, whether it resolves)
+    let cache = Arc::new(RwLock::new(None::<(Option<Word>, bool)>));
+
+    node.view = Some(
+        seg_view
+            .enumerate()
+            .map(move |(n, segment)| {
+                let n = n.clone();
+                let ctx = ctx.clone();
+                let list = list.clone();
+                let cache = cache.clone();
+
+                segment.map_item(move |_pos, a| {
+                    let list = list.read().unwrap();
+                    let n_assignments = (0..list.data.len())
+                        .take_while(|i| crate::arg::is_assignment(&ctx, &list.data.get(*i)))
+                        .count();
+                    let name = (n_assignments < list.data.len())
+                        .then(|| crate::arg::arg_word(&ctx, &list.data.get(n_assignments)));
+                    drop(list);
+
+                    let mut cache = cache.write().unwrap();
+                    let resolves = match cache.as_ref() {
+                        Some((cached_name, resolves)) if *cached_name == name => *resolves,
+                        _ => {
+                            let resolves = name.as_ref()
+                                .map(|name| crate::which::resolve(&name.to_arg(), None).is_ok())
+                                .unwrap_or(true);
+                            *cache = Some((name, resolves));
+                            resolves
+                        }
+                    };
+
+                    if n < n_assignments {
+                        // `EnvAssignment` nodes are styled by their own view
+                        *a
+                    } else if n == n_assignments {
+                        a.add_style_back(TerminalStyle::fg_color(
+                            if resolves { (100, 200, 100) } else { (200, 80, 80) }
+                        ))
+                    } else {
+                        *a
+                    }
+                })
+            })
+            .separate(make_label(" "))
+            .to_grid_horizontal()
+            .flatten()
+    );
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
 impl ProcessLauncher {
     pub fn init_ctx(ctx: &mut Context) {
         ctx.add_list_typename("ProcessArg".into());
@@ -52,6 +145,7 @@ impl ProcessLauncher {
                 |mut node, _dst_type:_| {
                     PTYListController::for_node( &mut node, None, None );
                     PTYListStyle::for_node( &mut node, ("","","") );
+                    crate::arg::ArgCommander::for_node( &mut node, crate::arg::word_openers(), None );
                     Some(node)
                 }
             )
@@ -82,8 +176,18 @@ impl ProcessLauncher {
                 |mut node, _dst_type:_| {
                     PTYListController::for_node( &mut node, Some(' '), None );
                     PTYListStyle::for_node( &mut node, (""," ","") );
+                    crate::arg::QuotedSplit::for_node( &mut node, ' ' );
+                    highlight_command_name( &mut node );
+
+                    let process_launcher = crate::process::ProcessLauncher::new(node.clone());
+                    node.diag = Some(process_launcher.diag_buf
+                                     .get_port()
+                                     .to_sequence());
+
+                    let launcher = Arc::new(RwLock::new(process_launcher));
+                    node.cmd.set(Some(launcher.clone() as Arc<RwLock<dyn ObjCommander + Send + Sync>>));
+                    node.editor.set(Some(launcher.clone() as Arc<dyn std::any::Any + Send + Sync>));
 
-                    let _process_launcher = crate::process::ProcessLauncher::new(node.clone());
                     Some(node)
                 }
             )
@@ -126,8 +230,13 @@ impl ProcessLauncher {
         );
 
         ProcessLauncher {
+            list: cmd_editor.get_edit::<ListEditor>().unwrap(),
+            inner: cmd_editor.cmd.get(),
             cmd_editor,
+            cwd: None,
+            diag_buf: VecBuffer::new(),
             pty: None,
+            job_id: None,
             _ptybox: AsciiBox::new(
                 cgmath::Vector2::new(0, 0),
                 pty_port.outer().map_item(|_, a: &TerminalAtom| {
@@ -143,11 +252,10 @@ impl ProcessLauncher {
         }
     }
 
-    pub fn launch_pty(&mut self) {
+    /// runs the process in a new PTY whose screen is shown in `port`
+    pub fn launch_pty(&mut self, port: &ViewPort<dyn TerminalView>) {
         let ctx = self.cmd_editor.ctx.clone();//.read().unwrap().clone().unwrap();
 
-        let mut strings = Vec::<String>::new();
-
         let data = self.cmd_editor.data.clone();
 
         let v = data.read().unwrap()
@@ -156,60 +264,187 @@ impl ProcessLauncher {
             .read().unwrap()
             .get_view::<dyn SequenceView<Item = NestedNode>>();
 
-            for i in 0..v.len().unwrap_or(0) {
-                let arg_data = v
-                    .get(&i)
-                    .unwrap()
-                    .data
-                    .clone();
-
-                    let arg_view = arg_data
-                        .read()
-                        .unwrap()
-                        .descend(Context::parse(&ctx, "<List Char>"))
-                        .unwrap()
-                        .read().unwrap()
-                        .get_view::<dyn SequenceView<Item = NestedNode>>()
-                        .unwrap();
-
-                    let arg = arg_view.iter().filter_map(
-                            |node| {
-                                if let Some(c_view) = node.data
-                                    .read().unwrap()
-                                    .get_view::<dyn SingletonView<Item = Option<char>>>()
-                                {
-                                    c_view.get()
-                                } else {
-                                    None
-                                }
-                            }
-                    ).collect::<String>();
-
-                    strings.push(arg);
+        let mut processes = crate::parse::Process::from_words(
+            (0..v.len().unwrap_or(0))
+                .filter_map(|i| v.get(&i))
+                .map(|arg| crate::arg::arg_word(&ctx, &arg))
+                .collect()
+        ).expand_aliases();
+
+        // an alias may stand for a whole pipeline
+        if processes.len() > 1 {
+            self.diag_buf.push(nested::diagnostics::make_error(make_label("alias expands to a pipeline")));
+            return;
+        }
+        let process = processes.pop().unwrap();
+        let (vars, argv) = process.expand();
+
+        if argv.len() > 0 {
+            self.diag_buf.clear();
+            if let Err(msg) = crate::which::check(&argv[0], self.cwd.as_deref()) {
+                self.diag_buf.push(nested::diagnostics::make_error(make_label(&msg)));
+                return;
             }
 
-        if strings.len() > 0 {
+            let size = crate::layout::output_size();
+
             // Spawn a shell into the pty
-            let mut cmd = crate::pty::CommandBuilder::new(strings[0].as_str());
-            cmd.args(&strings[1..]);
-            cmd.cwd(".");
+            let mut cmd = crate::pty::CommandBuilder::new(argv[0].as_str());
+            cmd.args(&argv[1..]);
+            cmd.cwd(match self.cwd.as_ref() {
+                Some(cwd) => std::path::PathBuf::from(cwd),
+                None => std::env::current_dir().unwrap_or(".".into())
+            });
+
+            cmd.env_clear();
+            for (name, value) in crate::env::vars() {
+                cmd.env(name, value);
+            }
+            cmd.env("TERM", "xterm-256color");
+            cmd.env("COLUMNS", size.x.to_string());
+            cmd.env("LINES", size.y.to_string());
+            for (name, value) in vars.iter() {
+                cmd.env(name, value);
+            }
 
             self.cmd_editor.goto(TreeCursor {
                 leaf_mode: ListCursorMode::Insert,
                 tree_addr: vec![],
             });
 
-            self.pty = PTY::new(
+            self.pty = match PTY::new(
                 cmd,
-                cgmath::Vector2::new(120, 40),
-                self.pty_port.inner(),
+                size,
+                port.inner(),
                 self.status_port.inner(),
-            );
+            ) {
+                Ok(pty) => Some(pty),
+                Err(err) => {
+                    self.diag_buf.push(nested::diagnostics::make_error(
+                        make_label(&format!("{}: {}", argv[0], err))
+                    ));
+                    None
+                }
+            };
+
+            self.set_origin(crate::layout::output_origin());
+
+            if let Some(pty) = self.pty.as_ref() {
+                let view = port.outer();
+                let input = pty.input();
+                pty.on_alt_screen(move |on| {
+                    if on {
+                        crate::layout::enter_fullscreen(view.clone(), input.clone());
+                    } else {
+                        crate::layout::leave_fullscreen();
+                    }
+                });
+
+                let pid = pty.pid() as i32;
+                let job_id = crate::job::add(pid, crate::export::process_to_sh(&process), true);
+                self.job_id = Some(job_id);
+
+                let input = pty.input();
+                crate::reaper::watch(pid, move |event| {
+                    // don't leave the terminal to a dead program
+                    if event.is_final() && input.alt_screen() {
+                        crate::layout::leave_fullscreen();
+                    }
+
+                    crate::job::set_state(job_id, match event {
+                        ChildEvent::Stopped(_) => crate::job::JobState::Stopped,
+                        ChildEvent::Continued => crate::job::JobState::Running,
+                        _ => crate::job::JobState::Done
+                    });
+                });
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        match self.status_port.outer().get_view().get() {
+            PTYStatus::Done { status: _ } => true,
+            _ => false
         }
     }
 
+    /// true while the PTY runs in the foreground, also right after `fg`
     pub fn is_captured(&self) -> bool {
-        self.pty.is_some() && !self.suspended
+        self.pty.is_some() && !self.is_done() &&
+            self.job_id.and_then(crate::job::get)
+                .map(|job| job.foreground && job.state == crate::job::JobState::Running)
+                .unwrap_or(!self.suspended)
+    }
+
+    pub fn job_id(&self) -> Option<usize> {
+        self.job_id
+    }
+
+    /// removes and returns the diagnostics of the last launch
+    pub fn take_diags(&mut self) -> Vec<nested::diagnostics::Message> {
+        let diags = (0..self.diag_buf.len()).map(|i| self.diag_buf.get(i)).collect();
+        self.diag_buf.clear();
+        diags
+    }
+
+    /// turns the argument at the cursor into an `EnvAssignment`,
+    /// if `=` is typed behind a name in front of the command
+    fn begin_assignment(&mut self) -> bool {
+        let ctx = self.cmd_editor.ctx.clone();
+        let cursor = self.list.read().unwrap().get_cursor();
+        let (idx, pos) = match cursor.tree_addr.as_slice() {
+            [idx, pos] if *idx >= 0 && *pos >= 0 => (*idx as usize, *pos as usize),
+            _ => { return false; }
+        };
+
+        let list = self.list.read().unwrap();
+        if (0..idx).any(|i| !crate::arg::is_assignment(&ctx, &list.data.get(i)))
+            || crate::arg::is_assignment(&ctx, &list.data.get(idx))
+        {
+            return false;
+        }
+        let text = match crate::arg::arg_word(&ctx, &list.data.get(idx)).as_literal() {
+            Some(text) => text.to_string(),
+            None => { return false; }
+        };
+        drop(list);
+
+        let name : String = text.chars().take(pos).collect();
+        let value : String = text.chars().skip(pos).collect();
+        if crate::parse::split_assignment(&format!("{}=", name)).is_none() {
+            return false;
+        }
+
+        let node = crate::arg::make_assignment_node(
+            &ctx,
+            &name,
+            &Word { pos: 0, parts: vec![ crate::parse::WordPart::Literal(value) ] },
+            self.cmd_editor.depth.map(|d| d + 1)
+        );
+
+        let mut list = self.list.write().unwrap();
+        list.data.remove(idx);
+        list.data.insert(idx, node);
+        list.goto(TreeCursor {
+            leaf_mode: ListCursorMode::Insert,
+            tree_addr: vec![idx as isize, name.chars().count() as isize + 1]
+        });
+        true
+    }
+
+    fn send_inner(&mut self, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+        match self.inner.as_ref() {
+            Some(inner) => inner.write().unwrap().send_cmd_obj(cmd_obj),
+            None => TreeNavResult::Exit
+        }
+    }
+
+    /// places the output box at `origin` on the screen, for mouse events
+    pub fn set_origin(&mut self, origin: cgmath::Point2<i16>) {
+        if let Some(pty) = self.pty.as_mut() {
+            // inside the box border
+            pty.set_origin(origin + cgmath::Vector2::new(1, 1));
+        }
     }
 
     pub fn pty_view(&self) -> OuterViewPort<dyn TerminalView> {
@@ -218,18 +453,25 @@ impl ProcessLauncher {
 }
 
 
-use nested::type_system::ReprTree;
-use nested::commander::ObjCommander;
-
 impl ObjCommander for ProcessLauncher {
     fn send_cmd_obj(&mut self, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
 
-        // todo: move to observer of status view
-        if let PTYStatus::Done { status: _ } = self.status_port.outer().get_view().get() {
+        // status view & job state were already updated by the reaper
+        if self.is_done() {
+            self.job_id = None;
             self.pty = None;
             self.suspended = false;
         }
 
+        // continued by `fg`
+        if self.suspended {
+            if let Some(job) = self.job_id.and_then(crate::job::get) {
+                if job.foreground && job.state == crate::job::JobState::Running {
+                    self.suspended = false;
+                }
+            }
+        }
+
 
         let ctx = self.cmd_editor.ctx.clone();
         let ctx = ctx.read().unwrap();
@@ -245,22 +487,11 @@ impl ObjCommander for ProcessLauncher {
                 let event = te_view.get();
                 
                 match event {
-                    TerminalEvent::Input(Event::Key(Key::Ctrl('c'))) => {
-                        // todo: sigterm instead of kill?
-                        if let Some(pty) = self.pty.as_mut() {
-                            pty.kill();
-                        }
-
-                        self.pty = None;
-                        self.suspended = false;
-                        self.cmd_editor.goto(TreeCursor {
-                            leaf_mode: ListCursorMode::Insert,
-                            tree_addr: vec![],
-                        });
-
-                        TreeNavResult::Exit
-                    }
                     TerminalEvent::Input(Event::Key(Key::Ctrl('z'))) => {
+                        if let Some(job_id) = self.job_id {
+                            crate::job::suspend(job_id);
+                            crate::job::set_state(job_id, crate::job::JobState::Stopped);
+                        }
                         self.suspended = true;
                         self.cmd_editor.goto(TreeCursor {
                             leaf_mode: ListCursorMode::Insert,
@@ -270,17 +501,28 @@ impl ObjCommander for ProcessLauncher {
                         TreeNavResult::Exit
                     }
                     event => {
+                        // the box moves with the commands above it
+                        if let TerminalEvent::Input(Event::Mouse(_)) = event {
+                            self.set_origin(crate::layout::output_origin());
+                        }
+
+                        // Ctrl-C goes in as ^C, so the line discipline
+                        // signals the PTY's foreground process group
                         if let Some(pty) = self.pty.as_mut() {
-                            pty.handle_terminal_event(&event);
+                            if let Err(err) = pty.handle_terminal_event(&event) {
+                                self.diag_buf.push(nested::diagnostics::make_error(
+                                    make_label(&err.to_string())
+                                ));
+                            }
                             TreeNavResult::Continue
                         } else {
                             match event {
                                 TerminalEvent::Input(Event::Key(Key::Char('\n'))) => {
                                     // launch command
-                                    self.launch_pty();
+                                    self.launch_pty(&self.pty_port.clone());
                                     TreeNavResult::Continue
                                 }
-                                _event => { self.cmd_editor.send_cmd_obj(cmd_obj) },
+                                _event => { self.send_inner(cmd_obj) },
                             }
                         }
                     }
@@ -295,18 +537,20 @@ impl ObjCommander for ProcessLauncher {
                 let c = cmd_view.get();
                 
                 if c == '\n' {
-                    self.launch_pty();
+                    self.launch_pty(&self.pty_port.clone());
                     TreeNavResult::Exit
+                } else if c == '=' && self.begin_assignment() {
+                    TreeNavResult::Continue
                 } else {
-                    self.cmd_editor.send_cmd_obj(cmd_obj)
+                    self.send_inner(cmd_obj)
                 }                
             } else {
                 drop(co);
-                self.cmd_editor.send_cmd_obj(cmd_obj)
+                self.send_inner(cmd_obj)
             }
         } else {
             drop(co);
-            self.cmd_editor.send_cmd_obj(cmd_obj)
+            self.send_inner(cmd_obj)
         }
     }
 }
@@ -72,30 +72,14 @@ impl ProcessTypes {
     }
 
     pub fn get_type(&self, cmd: &Vec<String>, item: &str) -> Option<TypeTerm> {
-        let db = String::from(env!("CARGO_MANIFEST_DIR")) + "/typedb";
-        let gt = String::from(env!("CARGO_MANIFEST_DIR")) + "/gettype.sh";
-        let stdout_typeladder_str = std::process::Command::new(gt)
-            .current_dir(env!("CARGO_MANIFEST_DIR"))
-            .arg(db)
-            .arg(cmd.join(" "))
-            .arg(item)
-            .output()
-            .ok()?
-            .stdout;
+        let typeladder_str = match cmd.first() {
+            // builtins declare their signature themselves
+            Some(name) if crate::builtin::get(name).is_some() =>
+                crate::builtin::signature_type(name, item)?,
+            _ => crate::typedb::lookup(&cmd.join(" "), item)?
+        };
 
-        let stdout_typeladder_str = String::from_utf8(stdout_typeladder_str).ok()?;
-/*
-        let editor = TypeTermEditor::new();
-
-        editor.goto(TreeCursor::home())
-        for c in stdout_typeladder_str.iter() {
-            editor.send_cmd_obj(ReprTree::from_char( &ctx, c ));
-        }
-
-        let typeterm = editor.get_typeterm();
-*/
-        let typeterm = TypeTerm::Ladder(vec![]);
-        Some(typeterm)
+        self.ctx.read().unwrap().type_term_from_str(&typeladder_str).ok()
     }
 
     pub fn get_stdin_type(&self, cmd: &Vec<String>) -> Option<TypeTerm> {
@@ -12,8 +12,9 @@ use {
     nested::{
         terminal::{TerminalEditorResult, TerminalEvent, TerminalView},
     },
-    std::sync::{Arc, Mutex},
-    termion::event::{Event, Key},
+    std::sync::{Arc, Mutex, Weak},
+    std::io::Write,
+    termion::event::{Event, MouseEvent},
 };
 
 pub use portable_pty::CommandBuilder;
@@ -23,7 +24,7 @@ pub use portable_pty::CommandBuilder;
 #[derive(Clone)]
 pub enum PTYStatus {
     Running { pid: u32 },
-    Done { status: portable_pty::ExitStatus },
+    Done { status: crate::reaper::ChildEvent },
 }
 
 impl Default for PTYStatus {
@@ -34,9 +35,133 @@ impl Default for PTYStatus {
 
 //<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
 
+#[derive(Debug)]
+pub enum PtyError {
+    Open(String),
+    Spawn(String),
+    Reader(String),
+    NoPid,
+    Write(std::io::Error),
+}
+
+impl std::fmt::Display for PtyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PtyError::Open(msg) => write!(f, "cannot open pty: {}", msg),
+            PtyError::Spawn(msg) => write!(f, "cannot spawn: {}", msg),
+            PtyError::Reader(msg) => write!(f, "cannot read from pty: {}", msg),
+            PtyError::NoPid => write!(f, "spawned process has no pid"),
+            PtyError::Write(err) => write!(f, "cannot write to pty: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PtyError {}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+type Master = Mutex<Box<dyn portable_pty::MasterPty + Send>>;
+
+/// masters of all live PTYs, to propagate terminal resizes
+static MASTERS: Mutex<Vec<Weak<Master>>> = Mutex::new(Vec::new());
+
+fn pty_size(size: Vector2<i16>) -> portable_pty::PtySize {
+    portable_pty::PtySize {
+        rows: size.y as u16,
+        cols: size.x as u16,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// resizes every live PTY, so that the programs inside reflow
+pub fn resize_all(size: Vector2<i16>) {
+    MASTERS.lock().unwrap().retain(|master| {
+        if let Some(master) = master.upgrade() {
+            master.lock().unwrap().resize(pty_size(size)).ok();
+            true
+        } else {
+            false
+        }
+    });
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// writes terminal events into a PTY,
+/// can be cloned to send input from outside of the `PTY` owner
+#[derive(Clone)]
+pub struct PTYInput {
+    master: Arc<Master>,
+    modes: Arc<crate::keys::TermModes>,
+
+    /// screen position of the top left cell of the PTY's output
+    origin: cgmath::Point2<i16>,
+    mouse_pressed: Arc<Mutex<Option<u8>>>,
+}
+
+impl PTYInput {
+    pub fn set_origin(&mut self, origin: cgmath::Point2<i16>) {
+        self.origin = origin;
+    }
+
+    pub fn alt_screen(&self) -> bool {
+        self.modes.alt_screen()
+    }
+
+    pub fn resize(&self, size: Vector2<i16>) {
+        self.master.lock().unwrap().resize(pty_size(size)).ok();
+    }
+
+    fn write(&self, bytes: &[u8]) -> Result<(), PtyError> {
+        self.master.lock().unwrap().write_all(bytes).map_err(PtyError::Write)
+    }
+
+    /// translates a mouse event into local coordinates,
+    /// events outside of the PTY area are not forwarded
+    fn handle_mouse_event(&self, event: &MouseEvent) -> Result<TerminalEditorResult, PtyError> {
+        let (x, y) = match event {
+            MouseEvent::Press(_, x, y) | MouseEvent::Release(x, y) | MouseEvent::Hold(x, y) => (*x, *y)
+        };
+        let x = x as i16 - self.origin.x;
+        let y = y as i16 - self.origin.y;
+
+        let size = self.master.lock().unwrap().get_size().ok();
+        let inside = x >= 1 && y >= 1 && size.map(|s| x as u16 <= s.cols && y as u16 <= s.rows).unwrap_or(true);
+
+        if !inside {
+            return Ok(TerminalEditorResult::Exit);
+        }
+
+        let mut pressed = self.mouse_pressed.lock().unwrap();
+        if let Some(bytes) = crate::keys::encode_mouse(event, x as u16, y as u16, &self.modes, &mut pressed) {
+            self.write(&bytes)?;
+        }
+        Ok(TerminalEditorResult::Continue)
+    }
+
+    pub fn handle_terminal_event(&self, event: &TerminalEvent) -> Result<TerminalEditorResult, PtyError> {
+        match event {
+            TerminalEvent::Input(Event::Key(key)) => {
+                if let Some(bytes) = crate::keys::encode_key(key, self.modes.app_cursor()) {
+                    self.write(&bytes)?;
+                    Ok(TerminalEditorResult::Continue)
+                } else {
+                    Ok(TerminalEditorResult::Exit)
+                }
+            }
+            TerminalEvent::Input(Event::Mouse(event)) => self.handle_mouse_event(event),
+            _ => Ok(TerminalEditorResult::Exit),
+        }
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
 pub struct PTY {
-    master: Mutex<Box<dyn portable_pty::MasterPty + Send>>,
-    child: Arc<Mutex<Box<dyn portable_pty::Child + Send + Sync>>>,
+    input: PTYInput,
+    _child: Box<dyn portable_pty::Child + Send + Sync>,
+    pid: u32,
 }
 
 impl PTY {
@@ -45,7 +170,7 @@ impl PTY {
         max_size: Vector2<i16>,
         term_port: InnerViewPort<dyn TerminalView>,
         status_port: InnerViewPort<dyn SingletonView<Item = PTYStatus>>,
-    ) -> Option<Self> {
+    ) -> Result<Self, PtyError> {
         // Create a new pty
         let pair = portable_pty::native_pty_system()
             .openpty(portable_pty::PtySize {
@@ -60,115 +185,74 @@ impl PTY {
                 pixel_width: 0,
                 pixel_height: 0,
             })
-            .unwrap();
-
-        if let Ok(child) = pair.slave.spawn_command(cmd) {
-            let mut reader = pair.master.try_clone_reader().unwrap();
-            let mut status_buf = SingletonBuffer::with_port(
-                PTYStatus::Running {
-                    pid: child.process_id().expect(""),
-                },
-                status_port,
-            );
-
-            let child = Arc::new(Mutex::new(child));
-
-            async_std::task::spawn_blocking(move || {
-                nested::terminal::ansi_parser::read_ansi_from(&mut reader, max_size, term_port);
-            });
-
-            async_std::task::spawn_blocking({
-                let child = child.clone();
-                move || loop {
-                    if let Ok(Some(status)) = child.lock().unwrap().try_wait() {
-                        status_buf.set(PTYStatus::Done { status });
-                        break;
-                    }
-
-                    std::thread::sleep(std::time::Duration::from_millis(10));
-                }
-            });
+            .map_err(|err| PtyError::Open(err.to_string()))?;
 
-            Some(PTY {
-                master: Mutex::new(pair.master),
-                child,
-            })
-        } else {
-            None
-        }
+        let child = pair.slave.spawn_command(cmd)
+            .map_err(|err| PtyError::Spawn(err.to_string()))?;
+
+        let pid = child.process_id().ok_or(PtyError::NoPid)?;
+
+        let modes = Arc::new(crate::keys::TermModes::default());
+        let mut reader = crate::keys::ModeSniffer::new(
+            pair.master.try_clone_reader()
+                .map_err(|err| PtyError::Reader(err.to_string()))?,
+            modes.clone()
+        );
+        let mut status_buf = SingletonBuffer::with_port(
+            PTYStatus::Running { pid },
+            status_port,
+        );
+
+        // the screen buffer must hold the whole terminal in case the PTY goes fullscreen
+        let screen_size = Vector2::new(
+            max_size.x.max(crate::layout::terminal_size().x),
+            max_size.y.max(crate::layout::terminal_size().y)
+        );
+
+        async_std::task::spawn_blocking(move || {
+            nested::terminal::ansi_parser::read_ansi_from(&mut reader, screen_size, term_port);
+        });
+
+        crate::reaper::watch(pid as i32, move |event| {
+            if event.is_final() {
+                status_buf.set(PTYStatus::Done { status: event });
+            }
+        });
+
+        let master = Arc::new(Mutex::new(pair.master));
+        MASTERS.lock().unwrap().push(Arc::downgrade(&master));
+
+        Ok(PTY {
+            input: PTYInput {
+                master,
+                modes,
+                origin: cgmath::Point2::new(0, 0),
+                mouse_pressed: Arc::new(Mutex::new(None)),
+            },
+            _child: child,
+            pid,
+        })
     }
 
-    pub fn kill(&mut self) {
-        self.child.lock().unwrap().kill().unwrap();
+    /// the child is spawned as session leader, so this is also its process group
+    pub fn pid(&self) -> u32 {
+        self.pid
     }
 
-    pub fn handle_terminal_event(&mut self, event: &TerminalEvent) -> TerminalEditorResult {
-        match event {
-            TerminalEvent::Input(Event::Key(Key::Char('\n'))) => {
-                self.master.lock().unwrap().write(&[13]).unwrap();
-                TerminalEditorResult::Continue
-            }
-            TerminalEvent::Input(Event::Key(Key::Char(c))) => {
-                write!(self.master.lock().unwrap(), "{}", c).unwrap();
-                TerminalEditorResult::Continue
-            }
-            TerminalEvent::Input(Event::Key(Key::Esc)) => {
-                self.master.lock().unwrap().write(&[0x1b]).unwrap();
-                TerminalEditorResult::Continue
-            }
-            TerminalEvent::Input(Event::Key(Key::Backspace)) => {
-                self.master.lock().unwrap().write(&[0x8]).unwrap();
-                TerminalEditorResult::Continue
-            }
-            TerminalEvent::Input(Event::Key(Key::F(n))) => {
-                self.master
-                    .lock()
-                    .unwrap()
-                    .write(&[
-                        0x1b,
-                        0x0a,
-                        match n {
-                            11 => 133,
-                            12 => 134,
-                            n => 58 + n,
-                        },
-                    ])
-                    .unwrap();
-                TerminalEditorResult::Continue
-            }
-            TerminalEvent::Input(Event::Key(Key::Up)) => {
-                self.master
-                    .lock()
-                    .unwrap()
-                    .write(&[b'\x1B', b'[', b'A'])
-                    .unwrap();
-                TerminalEditorResult::Continue
-            }
-            TerminalEvent::Input(Event::Key(Key::Down)) => {
-                self.master
-                    .lock()
-                    .unwrap()
-                    .write(&[b'\x1B', b'[', b'B'])
-                    .unwrap();
-                TerminalEditorResult::Continue
-            }
-            TerminalEvent::Input(Event::Key(Key::Right)) => {
-                self.master
-                    .lock()
-                    .unwrap()
-                    .write(&[b'\x1B', b'[', b'C'])
-                    .unwrap();
-                TerminalEditorResult::Continue
-            }
-            TerminalEvent::Input(Event::Key(Key::Left)) => {
-                self.master
-                    .lock()
-                    .unwrap()
-                    .write(&[b'\x1B', b'[', b'D'])
-                    .unwrap();
-                TerminalEditorResult::Continue
-            }
-            _ => TerminalEditorResult::Exit,
-        }
+    pub fn set_origin(&mut self, origin: cgmath::Point2<i16>) {
+        self.input.set_origin(origin);
+    }
+
+    pub fn input(&self) -> PTYInput {
+        self.input.clone()
+    }
+
+    /// called from the output reader whenever the child enters or leaves the alternate screen
+    pub fn on_alt_screen(&self, f: impl Fn(bool) + Send + Sync + 'static) {
+        self.input.modes.on_alt_screen(f);
+    }
+
+    pub fn handle_terminal_event(&mut self, event: &TerminalEvent) -> Result<TerminalEditorResult, PtyError> {
+        self.input.handle_terminal_event(event)
     }
 }
@@ -18,6 +18,8 @@ pub fn init_ctx(ctx: &mut Context) {
             |mut node, _dst_type:_| {
                 PTYListController::for_node( &mut node, None, None );
                 PTYListStyle::for_node( &mut node, ("\'","","\'") );
+                // everything is literal
+                crate::arg::ArgCommander::for_node( &mut node, vec![], Some('\'') );
                 Some(node)
             }
         )
@@ -46,6 +48,7 @@ pub fn init_ctx(ctx: &mut Context) {
             |mut node, _dst_type:_| {
                 PTYListController::for_node( &mut node, None, None );
                 PTYListStyle::for_node( &mut node, ("\"","","\"") );
+                crate::arg::ArgCommander::for_node( &mut node, vec![('$', "shell::Substitution")], Some('"') );
                 Some(node)
             }
         )
@@ -0,0 +1,183 @@
+use {
+    std::{
+        collections::HashMap,
+        sync::{Mutex, Once},
+        sync::atomic::{AtomicI32, Ordering}
+    }
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// state change of a child process as reported by `waitpid`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChildEvent {
+    Exited(i32),
+    Signaled(i32),
+    Stopped(i32),
+    Continued,
+}
+
+impl ChildEvent {
+    fn from_wait_status(status: i32) -> Option<Self> {
+        if libc::WIFEXITED(status) {
+            Some(ChildEvent::Exited(libc::WEXITSTATUS(status)))
+        } else if libc::WIFSIGNALED(status) {
+            Some(ChildEvent::Signaled(libc::WTERMSIG(status)))
+        } else if libc::WIFSTOPPED(status) {
+            Some(ChildEvent::Stopped(libc::WSTOPSIG(status)))
+        } else if libc::WIFCONTINUED(status) {
+            Some(ChildEvent::Continued)
+        } else {
+            None
+        }
+    }
+
+    /// the process is gone after this event
+    pub fn is_final(&self) -> bool {
+        match self {
+            ChildEvent::Exited(_) | ChildEvent::Signaled(_) => true,
+            _ => false
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        *self == ChildEvent::Exited(0)
+    }
+}
+
+type Callback = Box<dyn FnMut(ChildEvent) + Send>;
+
+/// callbacks of all watched pids
+static WATCHED: Mutex<Option<HashMap<i32, Vec<Callback>>>> = Mutex::new(None);
+
+/// write end of the self-pipe which wakes up the reaper thread
+static WAKE_FD: AtomicI32 = AtomicI32::new(-1);
+static INIT: Once = Once::new();
+
+extern "C" fn on_sigchld(_sig: libc::c_int) {
+    wake();
+}
+
+fn wake() {
+    let fd = WAKE_FD.load(Ordering::Relaxed);
+    if fd >= 0 {
+        let b = [0u8];
+        unsafe { libc::write(fd, b.as_ptr() as *const libc::c_void, 1); }
+    }
+}
+
+fn init() {
+    INIT.call_once(|| {
+        let mut fds = [0; 2];
+        unsafe {
+            if libc::pipe(fds.as_mut_ptr()) != 0 {
+                panic!("reaper: cannot create pipe: {}", std::io::Error::last_os_error());
+            }
+            for fd in fds.iter() {
+                libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            }
+            // a full pipe must not block the signal handler
+            libc::fcntl(fds[1], libc::F_SETFL, libc::O_NONBLOCK);
+        }
+        WAKE_FD.store(fds[1], Ordering::Relaxed);
+
+        unsafe {
+            let mut action : libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_sigchld as usize;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(libc::SIGCHLD, &action, std::ptr::null_mut());
+        }
+
+        let read_fd = fds[0];
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            loop {
+                let n = unsafe { libc::read(read_fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+                if n < 0 && std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+                    break;
+                }
+                reap();
+            }
+        });
+    });
+}
+
+/// collects the state changes of all watched pids.
+/// only watched pids are waited for, so children which are
+/// waited on elsewhere (e.g. by `std::process::Command::output`) are left alone.
+/// callbacks run after `WATCHED` is released, so they may take other locks.
+fn reap() {
+    let mut changed : Vec<(i32, Vec<ChildEvent>, Vec<Callback>)> = Vec::new();
+
+    {
+        let mut watched = WATCHED.lock().unwrap();
+        let watched = watched.get_or_insert_with(HashMap::new);
+
+        watched.retain(|pid, callbacks| {
+            let mut events = Vec::new();
+            let keep = loop {
+                let mut status = 0;
+                let r = unsafe {
+                    libc::waitpid(*pid, &mut status, libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED)
+                };
+
+                if r == *pid {
+                    if let Some(event) = ChildEvent::from_wait_status(status) {
+                        events.push(event);
+                        if event.is_final() {
+                            break false;
+                        }
+                    }
+                } else if r == 0 {
+                    break true;
+                } else {
+                    // not our child (anymore)
+                    break false;
+                }
+            };
+
+            if events.len() > 0 {
+                changed.push((*pid, events, std::mem::take(callbacks)));
+                // callbacks are put back below once they ran
+                false
+            } else {
+                keep
+            }
+        });
+    }
+
+    for (pid, events, mut callbacks) in changed {
+        for event in events.iter() {
+            for callback in callbacks.iter_mut() {
+                callback(*event);
+            }
+        }
+
+        if ! events.iter().any(|event| event.is_final()) {
+            let mut watched = WATCHED.lock().unwrap();
+            let watched_callbacks = watched.get_or_insert_with(HashMap::new)
+                .entry(pid)
+                .or_insert_with(Vec::new);
+
+            // keep the order of registration before callbacks added meanwhile
+            callbacks.append(watched_callbacks);
+            *watched_callbacks = callbacks;
+        }
+    }
+}
+
+/// calls `callback` from the reaper thread on every state change of `pid`
+/// until it exited.
+pub fn watch(pid: i32, callback: impl FnMut(ChildEvent) + Send + 'static) {
+    init();
+
+    WATCHED.lock().unwrap()
+        .get_or_insert_with(HashMap::new)
+        .entry(pid)
+        .or_insert_with(Vec::new)
+        .push(Box::new(callback));
+
+    // the child might have changed state before it was registered
+    wake();
+}
@@ -0,0 +1,69 @@
+use {
+    std::{
+        io::Read,
+        sync::mpsc::{channel, Receiver, Sender}
+    }
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// merges several byte sources (child output, echoed input)
+/// into one stream that can be fed into the ansi parser
+pub fn stream() -> (StreamWriter, StreamReader) {
+    let (tx, rx) = channel();
+    (
+        StreamWriter { tx },
+        StreamReader { rx, buf: Vec::new(), pos: 0 }
+    )
+}
+
+#[derive(Clone)]
+pub struct StreamWriter {
+    tx: Sender<Vec<u8>>,
+}
+
+impl StreamWriter {
+    pub fn write(&self, data: &[u8]) {
+        // the reader is gone when the output view was reset
+        self.tx.send(data.to_vec()).ok();
+    }
+
+    /// blocks until `reader` reaches EOF
+    pub fn copy_from(&self, mut reader: impl Read) {
+        let mut buf = [0; 4096];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => { break; }
+                Ok(n) => { self.write(&buf[..n]); }
+            }
+        }
+    }
+}
+
+/// reaches EOF once all writers are dropped
+pub struct StreamReader {
+    rx: Receiver<Vec<u8>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos >= self.buf.len() {
+            match self.rx.recv() {
+                Ok(buf) => {
+                    self.buf = buf;
+                    self.pos = 0;
+                }
+                Err(_) => {
+                    return Ok(0);
+                }
+            }
+        }
+
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos .. self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,218 @@
+use {
+    std::sync::{Arc, RwLock, Weak},
+    r3vi::{
+        view::{OuterViewPort, singleton::*, sequence::*},
+        buffer::{index_hashmap::*}
+    },
+    laddertypes::{TypeTerm},
+    nested::{
+        type_system::{Context, MorphismTypePattern, ReprTree},
+        editors::list::*,
+        terminal::{make_label, TerminalStyle, TerminalView, TerminalProjections},
+        tree::{NestedNode, TreeNavResult},
+        commander::ObjCommander
+    },
+    cgmath::Point2
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// longest value shown after a substitution
+const MAX_PREVIEW: usize = 24;
+
+fn char_of(node: &NestedNode) -> Option<char> {
+    node.get_data_view::<dyn SingletonView<Item = Option<char>>>(vec![].into_iter()).get()
+}
+
+/// the text of a `shell::Substitution` node without `$`, e.g. `HOME` or `{HOME}`
+pub fn substitution_text(node: &NestedNode) -> String {
+    let chars = node.get_data_view::<dyn SequenceView<Item = NestedNode>>(vec!["<List Char>"].into_iter());
+    (0..chars.len().unwrap_or(0))
+        .filter_map(|i| chars.get(&i))
+        .filter_map(|c| char_of(&c))
+        .collect()
+}
+
+/// the variable name in `text`, braces removed
+pub fn variable_name(text: &str) -> &str {
+    let name = text.strip_prefix('{').unwrap_or(text);
+    if text.starts_with('{') {
+        name.strip_suffix('}').unwrap_or(name)
+    } else {
+        name
+    }
+}
+
+/// whether `c` continues a substitution which reads `text` so far.
+/// like the parser, a digit or special char is a name of its own
+fn accepts(text: &str, c: char) -> bool {
+    if text.starts_with('{') {
+        let closed = text.len() > 1 && text.ends_with('}');
+        !closed && (c == '}' || crate::parse::is_name_char(c))
+    } else if text.len() == 0 {
+        c == '{' || crate::parse::is_name_char(c) || "?$!#@*-".contains(c)
+    } else {
+        text.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic()) && crate::parse::is_name_char(c)
+    }
+}
+
+/// current value of the variable, greyed
+fn make_preview(text: &str) -> OuterViewPort<dyn TerminalView> {
+    let name = variable_name(text);
+    if name.len() == 0 {
+        return make_label("");
+    }
+
+    let value = match crate::parse::special_parameter(name) {
+        // shown only if there is something to see
+        Some(value) if value.len() == 0 => { return make_label(""); }
+        Some(value) => Some(value),
+        None => crate::env::get(name)
+    };
+
+    match value {
+        Some(value) => {
+            let mut shown : String = value.chars().take(MAX_PREVIEW).collect();
+            if shown.len() < value.len() {
+                shown.push('…');
+            }
+            make_label(&format!("={}", shown)).with_fg_color((100, 100, 100))
+        }
+        None => make_label("=(unset)").with_fg_color((150, 90, 90))
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// the name and value shown by a substitution node
+struct Preview {
+    list: Arc<RwLock<ListEditor>>,
+    grid: IndexBuffer<Point2<i16>, OuterViewPort<dyn TerminalView>>,
+
+    /// of the variables when the value was taken
+    revision: u64,
+}
+
+impl Preview {
+    fn text(&self) -> String {
+        let list = self.list.read().unwrap();
+        (0..list.data.len())
+            .filter_map(|i| char_of(&list.data.get(i)))
+            .collect()
+    }
+
+    fn update(&mut self) {
+        self.revision = crate::env::revision();
+        self.grid.insert(Point2::new(2, 0), make_preview(&self.text()));
+    }
+}
+
+/// previews of all substitution nodes
+static PREVIEWS: RwLock<Vec<Weak<RwLock<Preview>>>> = RwLock::new(Vec::new());
+
+/// shows changes made by `export` & co. in all substitutions
+pub fn refresh_previews() {
+    let revision = crate::env::revision();
+    PREVIEWS.write().unwrap().retain(|preview| match preview.upgrade() {
+        Some(preview) => {
+            let mut preview = preview.write().unwrap();
+            if preview.revision != revision {
+                preview.update();
+            }
+            true
+        }
+        None => false
+    });
+}
+
+/// takes the chars of a variable name and returns `Exit`
+/// for any other char, which then belongs to the surrounding argument
+struct SubstitutionCommander {
+    ctx: Arc<RwLock<Context>>,
+    inner: Option<Arc<RwLock<dyn ObjCommander + Send + Sync>>>,
+    preview: Arc<RwLock<Preview>>,
+}
+
+impl ObjCommander for SubstitutionCommander {
+    fn send_cmd_obj(&mut self, cmd_obj: Arc<RwLock<ReprTree>>) -> TreeNavResult {
+        let co = cmd_obj.read().unwrap();
+        let char_value =
+            if co.get_type().clone() == Context::parse(&self.ctx, "Char") {
+                co.get_view::<dyn SingletonView<Item = char>>().map(|v| v.get())
+            } else {
+                None
+            };
+        drop(co);
+
+        if let Some(c) = char_value {
+            if !accepts(&self.preview.read().unwrap().text(), c) {
+                return TreeNavResult::Exit;
+            }
+        }
+
+        let result = match self.inner.as_ref() {
+            Some(inner) => inner.write().unwrap().send_cmd_obj(cmd_obj),
+            None => TreeNavResult::Exit
+        };
+
+        self.preview.write().unwrap().update();
+        result
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+pub fn init_ctx(ctx: &mut Context) {
+    ctx.add_list_typename("shell::Substitution".into());
+    ctx.add_morphism(
+        // <List Char>  =>  shell::Substitution
+        MorphismTypePattern {
+            src_tyid: ctx.get_typeid("List"),
+            dst_tyid: ctx.get_typeid("shell::Substitution").unwrap()
+        },
+        Arc::new(
+            |mut node, _dst_type:_| {
+                PTYListController::for_node( &mut node, None, None );
+                PTYListStyle::for_node( &mut node, ("","","") );
+
+                if let Some(list) = node.get_edit::<ListEditor>() {
+                    let name_view = PTYListStyle::new( ("", "", "") )
+                        .get_seg_seq_view( &mut list.write().unwrap() )
+                        .to_grid_horizontal()
+                        .flatten()
+                        .map_item(|_, a| a.add_style_back(TerminalStyle::fg_color((80, 180, 200))));
+
+                    let mut grid = IndexBuffer::new();
+                    grid.insert_iter(vec![
+                        (Point2::new(0, 0), make_label("$").with_fg_color((80, 180, 200))),
+                        (Point2::new(1, 0), name_view),
+                        (Point2::new(2, 0), make_label(""))
+                    ]);
+                    node.view = Some(grid.get_port().flatten());
+
+                    let preview = Arc::new(RwLock::new(Preview { list, grid, revision: 0 }));
+                    PREVIEWS.write().unwrap().push(Arc::downgrade(&preview));
+
+                    let commander = SubstitutionCommander {
+                        ctx: node.ctx.clone(),
+                        inner: node.cmd.get(),
+                        preview
+                    };
+                    node.cmd.set(Some(Arc::new(RwLock::new(commander)) as Arc<RwLock<dyn ObjCommander + Send + Sync>>));
+                }
+
+                Some(node)
+            }
+        )
+    );
+
+    ctx.add_node_ctor(
+        "shell::Substitution", Arc::new(
+            |ctx: Arc<RwLock<Context>>, dst_typ: TypeTerm, depth| {
+                let mut node = Context::make_node( &ctx, Context::parse(&ctx, "<List Char>"), depth ).unwrap();
+                node = node.morph(dst_typ);
+                Some(node)
+            }
+        )
+    );
+}
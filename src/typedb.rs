@@ -0,0 +1,686 @@
+use {
+    std::sync::{Arc, RwLock}
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// one element of a basic regular expression as used by `grep -x`.
+/// `\+`, `\?` and `\{m,n\}` are spelled out with stars and groups.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Bre {
+    Literal(char),
+
+    /// `.`
+    Any,
+
+    /// `[...]`, as (negated, ranges)
+    Class(bool, Vec<(char, char)>),
+
+    /// `x*`
+    Star(Box<Bre>),
+
+    /// `\(a\|b\)`
+    Group(Vec<Vec<Bre>>),
+}
+
+impl Bre {
+    fn matches_char(&self, c: char) -> bool {
+        match self {
+            Bre::Literal(l) => *l == c,
+            Bre::Any => true,
+            Bre::Class(negated, ranges) => {
+                ranges.iter().any(|(from, to)| *from <= c && c <= *to) != *negated
+            }
+            _ => false
+        }
+    }
+}
+
+/// ranges of a `[:name:]` class, for ASCII
+fn class_ranges(name: &str) -> Vec<(char, char)> {
+    match name {
+        "alpha" => vec![ ('A', 'Z'), ('a', 'z') ],
+        "digit" => vec![ ('0', '9') ],
+        "alnum" => vec![ ('0', '9'), ('A', 'Z'), ('a', 'z') ],
+        "upper" => vec![ ('A', 'Z') ],
+        "lower" => vec![ ('a', 'z') ],
+        "xdigit" => vec![ ('0', '9'), ('A', 'F'), ('a', 'f') ],
+        "space" => vec![ (' ', ' '), ('\t', '\r') ],
+        "blank" => vec![ (' ', ' '), ('\t', '\t') ],
+        "punct" => vec![ ('!', '/'), (':', '@'), ('[', '`'), ('{', '~') ],
+        "print" => vec![ (' ', '~') ],
+        "graph" => vec![ ('!', '~') ],
+        "cntrl" => vec![ ('\0', '\x1f'), ('\x7f', '\x7f') ],
+        _ => vec![]
+    }
+}
+
+/// the bounds of `\{m,n\}` after the opening brace, `None` for no upper bound
+fn parse_bounds(chars: &mut std::iter::Peekable<std::str::Chars>) -> (usize, Option<usize>) {
+    let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        let mut digits = String::new();
+        while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+            digits.push(*c);
+            chars.next();
+        }
+        digits.parse::<usize>().ok()
+    };
+
+    let min = number(chars).unwrap_or(0);
+    let max =
+        if chars.peek() == Some(&',') {
+            chars.next();
+            number(chars)
+        } else {
+            Some(min)
+        };
+
+    // `\}`
+    chars.next();
+    chars.next();
+
+    (min, max)
+}
+
+/// `item` repeated between `min` and `max` times
+fn repeat(item: Bre, min: usize, max: Option<usize>) -> Vec<Bre> {
+    let mut items = vec![ item.clone(); min ];
+    match max {
+        None => items.push(Bre::Star(Box::new(item))),
+        Some(max) => {
+            for _ in min..max {
+                items.push(Bre::Group(vec![ vec![ item.clone() ], vec![] ]));
+            }
+        }
+    }
+    items
+}
+
+/// parses a BRE up to the end of input or the end of the current group
+fn parse_alternatives(chars: &mut std::iter::Peekable<std::str::Chars>) -> Vec<Vec<Bre>> {
+    let mut alternatives = vec![ Vec::new() ];
+
+    while let Some(c) = chars.next() {
+        let seq = alternatives.last_mut().unwrap();
+
+        let item = match c {
+            '\\' => match chars.next() {
+                Some('(') => Bre::Group(parse_alternatives(chars)),
+                Some(')') => { return alternatives; }
+                Some('|') => { alternatives.push(Vec::new()); continue; }
+                Some(c @ '+') | Some(c @ '?') | Some(c @ '{') => match seq.pop() {
+                    Some(prev) => {
+                        let (min, max) = match c {
+                            '+' => (1, None),
+                            '?' => (0, Some(1)),
+                            _ => parse_bounds(chars)
+                        };
+                        seq.extend(repeat(prev, min, max));
+                        continue;
+                    }
+                    // nothing to repeat
+                    None => Bre::Literal(c)
+                },
+                Some(c) => Bre::Literal(c),
+                None => Bre::Literal('\\')
+            },
+            '.' => Bre::Any,
+            '[' => {
+                let negated = chars.peek() == Some(&'^');
+                if negated {
+                    chars.next();
+                }
+
+                let mut ranges = Vec::new();
+                let mut first = true;
+                while let Some(c) = chars.next() {
+                    if c == ']' && !first {
+                        break;
+                    }
+                    first = false;
+
+                    if c == '[' && chars.peek() == Some(&':') {
+                        chars.next();
+                        let mut name = String::new();
+                        while let Some(c) = chars.next() {
+                            if c == ':' && chars.peek() == Some(&']') {
+                                chars.next();
+                                break;
+                            }
+                            name.push(c);
+                        }
+                        ranges.extend(class_ranges(&name));
+                        continue;
+                    }
+
+                    if chars.peek() == Some(&'-') {
+                        chars.next();
+                        match chars.peek() {
+                            Some(&']') | None => {
+                                ranges.push((c, c));
+                                ranges.push(('-', '-'));
+                            }
+                            Some(&to) => {
+                                chars.next();
+                                ranges.push((c, to));
+                            }
+                        }
+                    } else {
+                        ranges.push((c, c));
+                    }
+                }
+                Bre::Class(negated, ranges)
+            }
+            '*' => match seq.pop() {
+                Some(prev) => Bre::Star(Box::new(prev)),
+                // a leading star is literal
+                None => Bre::Literal('*')
+            },
+            '^' if seq.len() == 0 => { continue; }
+            '$' if chars.peek().is_none() => { continue; }
+            c => Bre::Literal(c)
+        };
+
+        seq.push(item);
+    }
+
+    alternatives
+}
+
+pub fn parse_bre(pattern: &str) -> Vec<Bre> {
+    let mut chars = pattern.chars().peekable();
+    let mut alternatives = parse_alternatives(&mut chars);
+
+    if alternatives.len() == 1 {
+        alternatives.pop().unwrap()
+    } else {
+        vec![ Bre::Group(alternatives) ]
+    }
+}
+
+fn match_item(item: &Bre, s: &[char], pos: usize, k: &dyn Fn(usize) -> bool) -> bool {
+    match item {
+        Bre::Group(alternatives) => alternatives.iter().any(|alt| match_seq(alt, s, pos, k)),
+        Bre::Star(inner) => {
+            k(pos) ||
+            match_item(inner, s, pos, &|p| p > pos && match_item(item, s, p, k))
+        }
+        item => pos < s.len() && item.matches_char(s[pos]) && k(pos + 1)
+    }
+}
+
+fn match_seq(items: &[Bre], s: &[char], pos: usize, k: &dyn Fn(usize) -> bool) -> bool {
+    match items.split_first() {
+        Some((item, rest)) => match_item(item, s, pos, &|p| match_seq(rest, s, p, k)),
+        None => k(pos)
+    }
+}
+
+/// like `grep -x`, the whole string has to match
+pub fn bre_matches(bre: &[Bre], s: &str) -> bool {
+    let chars : Vec<char> = s.chars().collect();
+    match_seq(bre, &chars, 0, &|p| p == chars.len())
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// a block of the typedb: a pattern for the command line
+/// and the type ladders of its streams, e.g. `>0` or `<1`
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub pattern: String,
+    pub bre: Vec<Bre>,
+    pub types: Vec<(String, String)>,
+}
+
+impl Entry {
+    pub fn get(&self, item: &str) -> Option<&str> {
+        self.types.iter()
+            .find(|(i, _)| i == item)
+            .map(|(_, ladder)| ladder.as_str())
+    }
+
+    pub fn matches(&self, cmd: &str) -> bool {
+        bre_matches(&self.bre, cmd)
+    }
+
+    /// the literal command names at the start of the pattern,
+    /// e.g. `head` and `tail` for `\(head\|tail\)\(\| .*\)`
+    pub fn command_names(&self) -> Vec<String> {
+        let mut names = vec![ String::new() ];
+
+        for item in self.bre.iter() {
+            match item {
+                Bre::Literal(' ') => { break; }
+                Bre::Literal(c) => {
+                    for name in names.iter_mut() {
+                        name.push(*c);
+                    }
+                }
+                Bre::Group(alternatives) if alternatives.iter().all(
+                    |alt| alt.len() > 0 && alt.iter().all(|item| match item {
+                        Bre::Literal(c) => *c != ' ',
+                        _ => false
+                    })
+                ) => {
+                    names = names.iter()
+                        .flat_map(|name| alternatives.iter().map(move |alt| {
+                            let mut name = name.clone();
+                            for item in alt.iter() {
+                                if let Bre::Literal(c) = item {
+                                    name.push(*c);
+                                }
+                            }
+                            name
+                        }))
+                        .collect();
+                }
+                _ => { break; }
+            }
+        }
+
+        names.retain(|name| name.len() > 0);
+        names
+    }
+}
+
+pub fn parse(text: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+
+    for line in text.lines() {
+        if let Some(pattern) = line.strip_prefix("::") {
+            entries.push(Entry {
+                pattern: pattern.into(),
+                bre: parse_bre(pattern),
+                types: Vec::new()
+            });
+        } else if let Some(entry) = entries.last_mut() {
+            if let Some((item, ladder)) = line.split_once(": ") {
+                entry.types.push((item.into(), ladder.into()));
+            }
+        }
+    }
+
+    entries
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+static ENTRIES: RwLock<Option<Arc<Vec<Entry>>>> = RwLock::new(None);
+
+/// all entries of the typedb, read on first use
+pub fn entries() -> Arc<Vec<Entry>> {
+    if let Some(entries) = ENTRIES.read().unwrap().as_ref() {
+        return entries.clone();
+    }
+
+    let path = String::from(env!("CARGO_MANIFEST_DIR")) + "/typedb";
+    let entries = Arc::new(parse(&std::fs::read_to_string(path).unwrap_or_default()));
+    *ENTRIES.write().unwrap() = Some(entries.clone());
+    entries
+}
+
+/// type ladder of `item` from the first matching entry which has one
+pub fn lookup(cmd: &str, item: &str) -> Option<String> {
+    entries().iter()
+        .filter(|entry| entry.matches(cmd))
+        .find_map(|entry| entry.get(item))
+        .map(|ladder| ladder.into())
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+/// deepest nesting of groups and repetitions that is followed
+const MAX_DEPTH: usize = 32;
+
+/// classes with more characters than this are not enumerated
+const MAX_CLASS_LEN: usize = 8;
+
+impl std::fmt::Display for Bre {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Bre::Literal(c) => write!(f, "{}", c),
+            Bre::Any => write!(f, "."),
+            Bre::Class(negated, ranges) => {
+                write!(f, "[{}", if *negated { "^" } else { "" })?;
+                for (from, to) in ranges.iter() {
+                    if from == to {
+                        write!(f, "{}", from)?;
+                    } else {
+                        write!(f, "{}-{}", from, to)?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Bre::Star(inner) => write!(f, "{}*", inner),
+            Bre::Group(alternatives) => {
+                write!(f, "\\(")?;
+                for (i, alt) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "\\|")?;
+                    }
+                    for item in alt.iter() {
+                        write!(f, "{}", item)?;
+                    }
+                }
+                write!(f, "\\)")
+            }
+        }
+    }
+}
+
+/// remaining items of a partial match, innermost group last
+type Stack<'a> = Vec<&'a [Bre]>;
+
+/// takes the next item off the stack
+fn next_item<'a>(stack: &mut Stack<'a>) -> Option<(&'a Bre, &'a [Bre])> {
+    while let Some(items) = stack.pop() {
+        if let Some(item) = items.first() {
+            return Some((item, items));
+        }
+    }
+    None
+}
+
+/// all stacks which remain after matching the whole of `s`
+fn consume<'a>(mut stack: Stack<'a>, s: &[char], out: &mut Vec<Stack<'a>>) {
+    if stack.len() > MAX_DEPTH {
+        return;
+    }
+    if s.len() == 0 {
+        // everything from here on is up to `expand`
+        out.push(stack);
+        return;
+    }
+
+    if let Some((item, items)) = next_item(&mut stack) {
+        let rest = &items[1..];
+        match item {
+            Bre::Group(alternatives) => {
+                for alt in alternatives.iter() {
+                    let mut stack = stack.clone();
+                    stack.push(rest);
+                    stack.push(alt);
+                    consume(stack, s, out);
+                }
+            }
+            Bre::Star(inner) => {
+                // zero times
+                let mut zero = stack.clone();
+                zero.push(rest);
+                consume(zero, s, out);
+
+                // once more, if there is still input
+                if s.len() > 0 {
+                    let mut more = stack.clone();
+                    more.push(items);
+                    more.push(std::slice::from_ref(&**inner));
+                    consume(more, s, out);
+                }
+            }
+            item => {
+                if s.len() > 0 && item.matches_char(s[0]) {
+                    stack.push(rest);
+                    consume(stack, &s[1..], out);
+                }
+            }
+        }
+    }
+}
+
+/// how the rest of the current word may look like
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Continuation {
+    /// a whole word
+    Word(String),
+
+    /// the word continues with something that can't be enumerated,
+    /// e.g. an operand like `[a-z/]*`
+    Slot(String, String),
+}
+
+fn expand<'a>(mut stack: Stack<'a>, word: String, out: &mut Vec<Continuation>) {
+    if stack.len() > MAX_DEPTH {
+        return;
+    }
+
+    match next_item(&mut stack) {
+        None => out.push(Continuation::Word(word)),
+        Some((item, items)) => {
+            let rest = &items[1..];
+            match item {
+                Bre::Literal(' ') => out.push(Continuation::Word(word)),
+                Bre::Literal(c) => {
+                    stack.push(rest);
+                    let mut word = word;
+                    word.push(*c);
+                    expand(stack, word, out);
+                }
+                Bre::Class(false, ranges) if ranges.iter()
+                    .map(|(from, to)| (*to as u32).saturating_sub(*from as u32) as usize + 1)
+                    .sum::<usize>() <= MAX_CLASS_LEN =>
+                {
+                    for (from, to) in ranges.iter() {
+                        for c in *from ..= *to {
+                            let mut stack = stack.clone();
+                            stack.push(rest);
+                            let mut word = word.clone();
+                            word.push(c);
+                            expand(stack, word, out);
+                        }
+                    }
+                }
+                Bre::Group(alternatives) => {
+                    for alt in alternatives.iter() {
+                        let mut stack = stack.clone();
+                        stack.push(rest);
+                        stack.push(alt);
+                        expand(stack, word.clone(), out);
+                    }
+                }
+                Bre::Star(inner) if **inner == Bre::Literal(' ') ||
+                    match &**inner { Bre::Group(alts) => alts.iter().all(|alt| alt.first() == Some(&Bre::Literal(' '))), _ => false } =>
+                {
+                    // optional further arguments
+                    out.push(Continuation::Word(word));
+                }
+                // anything goes, nothing worth offering
+                Bre::Any => {}
+                Bre::Star(inner) if **inner == Bre::Any => {}
+
+                item => {
+                    let mut slot = item.to_string();
+                    for item in rest.iter().take_while(|item| **item != Bre::Literal(' ')) {
+                        slot.push_str(&item.to_string());
+                    }
+                    out.push(Continuation::Slot(word, slot));
+                }
+            }
+        }
+    }
+}
+
+impl Entry {
+    /// ways to continue the word at the end of `typed`,
+    /// empty if `typed` can't be the start of a matching command line
+    pub fn continuations(&self, typed: &str) -> Vec<Continuation> {
+        let chars : Vec<char> = typed.chars().collect();
+
+        let mut stacks = Vec::new();
+        consume(vec![ self.bre.as_slice() ], &chars, &mut stacks);
+
+        let mut out = Vec::new();
+        for stack in stacks {
+            expand(stack, String::new(), &mut out);
+        }
+        out.dedup();
+        out
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, samples: &[&str]) -> Vec<String> {
+        let bre = parse_bre(pattern);
+        samples.iter()
+            .filter(|s| bre_matches(&bre, s))
+            .map(|s| String::from(*s))
+            .collect()
+    }
+
+    /// the patterns of the typedb which match each command line, as with `grep -x`
+    #[test]
+    fn typedb_patterns() {
+        let entries = entries();
+        assert!(entries.len() > 0);
+
+        for (cmd, patterns) in vec![
+            ("", vec![]),
+            ("cp a b", vec![ r#"cp\( .*\)*"# ]),
+            ("cpx", vec![]),
+            ("date", vec![ r#"date"# ]),
+            ("date +%s", vec![ r#"date +%s"# ]),
+            ("wc", vec![]),
+            ("wc -l", vec![ r#"wc \(-l\|--lines\)"# ]),
+            ("wc --bytes", vec![ r#"wc \(-[cmw]\|--bytes\|--chars\|--words\)"# ]),
+            ("wc -lx", vec![]),
+            ("seq 10", vec![ r#"seq [0-9]*"# ]),
+            ("seq -s : 5", vec![ r#"seq \(-s\|--separator\) : [0-9]*"# ]),
+            ("sort", vec![ r#"sort\( .*\)*"# ]),
+            ("sort -n", vec![ r#"sort -n\( .*\)*"#, r#"sort\( .*\)*"# ]),
+            ("sort -M x", vec![ r#"sort -M\( .*\)*"#, r#"sort\( .*\)*"# ]),
+            ("head -n 3", vec![ r#"\(head\|tail\)\(\| .*\)"# ]),
+            ("tail", vec![ r#"\(head\|tail\)\(\| .*\)"# ]),
+            ("cut -d: -f1", vec![ r#"cut -d[ ]*: -f[0-9]*"# ]),
+            ("cut -d / -f2", vec![ r#"cut -d[ ]*/ -f[0-9]*"# ]),
+            ("ls", vec![ r#"ls\( .*\)*"# ]),
+            ("ls -l", vec![ r#"ls\( .*\)*"# ]),
+            ("ls -a -l x", vec![ r#"ls\( .*\)* -l\( .*\)"#, r#"ls\( .*\)*"# ]),
+            ("rev", vec![ r#"rev"# ]),
+            ("grep x", vec![]),
+            ("xargs expr 2 +", vec![ r#"xargs expr [0-9]* \(+\|-\|\*\|/\)"# ]),
+            ("xargs -n1 expr 5 *", vec![
+                r#"xargs -n[ ]*1 expr [0-9]* \(+\|-\|\*\|/\)"#,
+                r#"xargs -n[ ]*1 expr [0-9]* \(+\|-\|\*\|/\)"#,
+            ]),
+            ("xargs -n 1 expr 5 \\*", vec![]),
+            ("xargs -I@ expr 500 + \"(\" @ % 7 \")\"", vec![
+                r#"xargs -I[ ]*@ expr 500 + "(" @ % [0-9]* ")""#,
+            ]),
+            ("xargs -d: -n1 expr 3 -", vec![
+                r#"xargs \(\(-d[ ]*:\|--delimiter=:\) -n[ ]*1\|-n[ ]*1 \(-d[ ]*:\|--delimiter=:\)\) expr [0-9]* \(+\|-\|\*\|/\)"#,
+            ]),
+            ("xargs --delimiter=: -n 1 expr 3 /", vec![
+                r#"xargs \(\(-d[ ]*:\|--delimiter=:\) -n[ ]*1\|-n[ ]*1 \(-d[ ]*:\|--delimiter=:\)\) expr [0-9]* \(+\|-\|\*\|/\)"#,
+            ]),
+            ("xargs -I@ -n1 dc -e \"2o10i@p\"", vec![
+                r#"xargs -I[ ]*@ -n[ ]*1 dc -e \("\|\)2[ ]*o[ ]*[0-9]*[ ]*i[ ]*@[ ]*p\("\|\)"#,
+                r#"xargs -I[ ]*@ -n[ ]*1 dc -e \("\|\)[0-9]*[ ]*o[ ]*10[ ]*i[ ]*@[ ]*p\("\|\)"#,
+            ]),
+            ("xargs -I @ -n 1 dc -e 16o10i@p", vec![
+                r#"xargs -I[ ]*@ -n[ ]*1 dc -e \("\|\)16[ ]*o[ ]*[0-9]*[ ]*i[ ]*@[ ]*p\("\|\)"#,
+                r#"xargs -I[ ]*@ -n[ ]*1 dc -e \("\|\)[0-9]*[ ]*o[ ]*10[ ]*i[ ]*@[ ]*p\("\|\)"#,
+            ]),
+            ("stat -c %Y /tmp", vec![ r#"stat -c %[WXYZ] [a-z/]*"# ]),
+            ("stat -c %y /tmp", vec![ r#"stat -c %[wxyz] [a-z/]*"# ]),
+            ("xargs stat -c %W", vec![ r#"xargs stat -c %[WXYZ]"# ]),
+            ("xargs -d: stat -c %x", vec![ r#"xargs -d[ ]*: stat -c %[wxyz]"# ]),
+            ("xargs rm -rf", vec![ r#"xargs rm\( .*\)*"# ]),
+            ("echo $PATH", vec![ r#"echo \($PATH\|"$PATH"\)"# ]),
+            ("echo \"$PATH\"", vec![ r#"echo \($PATH\|"$PATH"\)"# ]),
+            ("echo \"$RANDOM\"", vec![ r#"echo \($RANDOM\|"$RANDOM"\)"# ]),
+            ("date +%S", vec![ r#"date +%S"# ]),
+            ("xargs -I @ -n1 dc -e \"8o16i@p\"", vec![
+                r#"xargs -I[ ]*@ -n[ ]*1 dc -e \("\|\)8[ ]*o[ ]*[0-9]*[ ]*i[ ]*@[ ]*p\("\|\)"#,
+                r#"xargs -I[ ]*@ -n[ ]*1 dc -e \("\|\)[0-9]*[ ]*o[ ]*16[ ]*i[ ]*@[ ]*p\("\|\)"#,
+            ]),
+            ("xargs -I@ -n 1 dc -e 10o2i@p", vec![
+                r#"xargs -I[ ]*@ -n[ ]*1 dc -e \("\|\)10[ ]*o[ ]*[0-9]*[ ]*i[ ]*@[ ]*p\("\|\)"#,
+                r#"xargs -I[ ]*@ -n[ ]*1 dc -e \("\|\)[0-9]*[ ]*o[ ]*2[ ]*i[ ]*@[ ]*p\("\|\)"#,
+            ]),
+            ("xargs stat -c %w", vec![ r#"xargs stat -c %[wxyz]"# ]),
+            ("xargs -d : stat -c %Z", vec![ r#"xargs -d[ ]*: stat -c %[WXYZ]"# ]),
+            ("find", vec![]),
+            ("find . -printf '%TA\\n'", vec![
+                r#"find .*-printf \("\|'\|\)%[ABT]A\\n\("\|'\|\)\( .*\)*"#,
+                r#"find .*"#,
+            ]),
+            ("find / -printf %p:", vec![
+                r#"find .*-printf \("\|'\|\)%p:\("\|'\|\)\( .*\)*"#,
+                r#"find .*"#,
+            ]),
+            ("find . -printf %p\\n", vec![
+                r#"find .*-printf \("\|'\|\)%p\\n\("\|'\|\)\( .*\)*"#,
+                r#"find .*"#,
+            ]),
+            ("find . -printf \"%Bb:\" -x", vec![
+                r#"find .*-printf \("\|'\|\)%[ABT]b:\("\|'\|\)\( .*\)*"#,
+                r#"find .*"#,
+            ]),
+            ("find / -printf '%d\\n'", vec![
+                r#"find .*-printf \("\|'\|\)%d\\n\("\|'\|\)\( .*\)*"#,
+                r#"find .*"#,
+            ]),
+            ("find -printf %Tc\\n", vec![
+                r#"find .*-printf \("\|'\|\)%[ABT]c\\n\("\|'\|\)\( .*\)*"#,
+                r#"find .*"#,
+            ]),
+        ] {
+            let found : Vec<&str> = entries.iter()
+                .filter(|entry| entry.matches(cmd))
+                .map(|entry| entry.pattern.as_str())
+                .collect();
+            assert_eq!(found, patterns, "{}", cmd);
+        }
+    }
+
+    /// `\+`, `\?`, `\{m,n\}` and bracket classes, matched like GNU `grep -x`
+    #[test]
+    fn extensions() {
+        let samples = &[
+            "", "a", "aa", "aaa", "aaaa", "b", "ab", "aab", "abb",
+            "12-ab", "1-ab", "12-", "123-x", "xaby", "xababy", "xy",
+            "a b", "ab\t", "]a]", "a-", "-a", "+", "?", "{1}", "a{1}",
+            "F00", "f0g", "A.b", "a;b",
+        ];
+
+        for (pattern, expected) in vec![
+            (r"a\{2,3\}", vec![ "aa", "aaa" ]),
+            (r"a\{2\}", vec![ "aa" ]),
+            (r"a\{2,\}", vec![ "aa", "aaa", "aaaa" ]),
+            (r"a\{,2\}b*", vec![ "", "a", "aa", "b", "ab", "aab", "abb" ]),
+            (r"a\+b\?", vec![ "a", "aa", "aaa", "aaaa", "ab", "aab" ]),
+            (r"a*b\+", vec![ "b", "ab", "aab", "abb" ]),
+            (r"\+", vec![ "+" ]),
+            (r"\?", vec![ "?" ]),
+            (r"a{1}", vec![ "a{1}" ]),
+            (r"\{1\}", vec![ "{1}" ]),
+            (r"[[:digit:]]\{2\}-[[:alpha:]]*", vec![ "12-ab", "12-" ]),
+            (r"x\(ab\)\{1,\}y", vec![ "xaby", "xababy" ]),
+            (r"x\(ab\)\?y", vec![ "xaby", "xy" ]),
+            (r"[^[:space:]]*", vec![
+                "", "a", "aa", "aaa", "aaaa", "b", "ab", "aab", "abb", "12-ab",
+                "1-ab", "12-", "123-x", "xaby", "xababy", "xy", "]a]", "a-",
+                "-a", "+", "?", "{1}", "a{1}", "F00", "f0g", "A.b", "a;b"
+            ]),
+            (r"[[:alpha:][:space:]]*", vec![
+                "", "a", "aa", "aaa", "aaaa", "b", "ab", "aab", "abb", "xaby",
+                "xababy", "xy", "a b", "ab\t"
+            ]),
+            (r"[]a]*", vec![ "", "a", "aa", "aaa", "aaaa", "]a]" ]),
+            (r"[a-]*", vec![ "", "a", "aa", "aaa", "aaaa", "a-", "-a" ]),
+            (r"[-a]*", vec![ "", "a", "aa", "aaa", "aaaa", "a-", "-a" ]),
+            (r"[[:xdigit:]]*", vec![
+                "", "a", "aa", "aaa", "aaaa", "b", "ab", "aab", "abb", "F00"
+            ]),
+            (r"[[:upper:]][[:punct:]][[:lower:]]", vec![ "A.b" ]),
+            (r"\(a\|b\)\{2\}", vec![ "aa", "ab" ]),
+        ] {
+            assert_eq!(matches(pattern, samples), expected, "{}", pattern);
+        }
+    }
+}
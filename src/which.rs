@@ -0,0 +1,193 @@
+use {
+    std::{
+        collections::HashMap,
+        os::unix::fs::PermissionsExt,
+        path::{Path, PathBuf},
+        sync::RwLock
+    }
+};
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+static ALIASES: RwLock<Option<HashMap<String, String>>> = RwLock::new(None);
+
+/// executable names on `$PATH`, cached per value of `$PATH`
+static PATH_NAMES: RwLock<Option<(String, Vec<String>)>> = RwLock::new(None);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Resolution {
+    Builtin,
+    Alias(String),
+    Executable(PathBuf),
+    NotExecutable(PathBuf),
+    NotFound,
+}
+
+impl Resolution {
+    pub fn is_ok(&self) -> bool {
+        match self {
+            Resolution::Builtin | Resolution::Alias(_) | Resolution::Executable(_) => true,
+            _ => false
+        }
+    }
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+pub fn set_alias(name: &str, value: &str) {
+    ALIASES.write().unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(name.into(), value.into());
+}
+
+pub fn remove_alias(name: &str) -> Option<String> {
+    ALIASES.write().unwrap().as_mut().and_then(|aliases| aliases.remove(name))
+}
+
+pub fn get_alias(name: &str) -> Option<String> {
+    ALIASES.read().unwrap().as_ref().and_then(|aliases| aliases.get(name).cloned())
+}
+
+pub fn aliases() -> Vec<(String, String)> {
+    let mut aliases = ALIASES.read().unwrap().as_ref()
+        .map(|aliases| aliases.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<Vec<_>>())
+        .unwrap_or_default();
+    aliases.sort();
+    aliases
+}
+
+//<<<<>>>><<>><><<>><<<*>>><<>><><<>><<<<>>>>
+
+fn is_executable(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+fn path_var() -> String {
+    crate::env::get("PATH").unwrap_or_default()
+}
+
+/// resolves a command name like the shell would when running it.
+/// names containing a `/` are taken relative to `cwd`.
+pub fn resolve(name: &str, cwd: Option<&str>) -> Resolution {
+    if name.contains('/') {
+        let path = match cwd {
+            Some(cwd) => Path::new(cwd).join(name),
+            None => PathBuf::from(name)
+        };
+
+        return if is_executable(&path) {
+            Resolution::Executable(path)
+        } else if path.exists() {
+            Resolution::NotExecutable(path)
+        } else {
+            Resolution::NotFound
+        };
+    }
+
+    if let Some(value) = get_alias(name) {
+        return Resolution::Alias(value);
+    }
+    if crate::builtin::get(name).is_some() {
+        return Resolution::Builtin;
+    }
+
+    let mut not_executable = None;
+    for dir in path_var().split(':').filter(|d| d.len() > 0) {
+        let path = Path::new(dir).join(name);
+        if is_executable(&path) {
+            return Resolution::Executable(path);
+        } else if not_executable.is_none() && path.is_file() {
+            not_executable = Some(path);
+        }
+    }
+
+    match not_executable {
+        Some(path) => Resolution::NotExecutable(path),
+        None => Resolution::NotFound
+    }
+}
+
+/// all executable names on `$PATH`, sorted and without duplicates
+pub fn path_names() -> Vec<String> {
+    let path = path_var();
+    if let Some((cached_path, names)) = PATH_NAMES.read().unwrap().as_ref() {
+        if *cached_path == path {
+            return names.clone();
+        }
+    }
+
+    let mut names = Vec::new();
+    for dir in path.split(':').filter(|d| d.len() > 0) {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                if is_executable(&entry.path()) {
+                    if let Ok(name) = entry.file_name().into_string() {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+
+    *PATH_NAMES.write().unwrap() = Some((path, names.clone()));
+    names
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a : Vec<char> = a.chars().collect();
+    let b : Vec<char> = b.chars().collect();
+    let mut row : Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut prev = row[0];
+        row[0] = i;
+        for j in 1..=b.len() {
+            let cur = row[j];
+            row[j] = if a[i-1] == b[j-1] {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j-1])
+            };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+/// the closest command names to a mistyped `name`
+pub fn suggest(name: &str) -> Vec<String> {
+    let max_dist = (name.chars().count() / 3).max(1);
+
+    let mut candidates : Vec<(usize, String)> = path_names().into_iter()
+        .chain(crate::builtin::names().into_iter().map(|b| b.to_string()))
+        .chain(aliases().into_iter().map(|(name, _)| name))
+        .map(|candidate| (edit_distance(name, &candidate), candidate))
+        .filter(|(dist, _)| *dist <= max_dist)
+        .collect();
+
+    candidates.sort();
+    candidates.dedup();
+    candidates.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+/// resolves `name`, or describes why it can't be run
+pub fn check(name: &str, cwd: Option<&str>) -> Result<Resolution, String> {
+    match resolve(name, cwd) {
+        Resolution::NotFound => {
+            let suggestions = suggest(name);
+            if suggestions.len() > 0 {
+                Err(format!("{}: command not found, did you mean {}?", name,
+                            suggestions.iter().map(|s| format!("`{}`", s)).collect::<Vec<_>>().join(", ")))
+            } else {
+                Err(format!("{}: command not found", name))
+            }
+        }
+        Resolution::NotExecutable(path) =>
+            Err(format!("{}: permission denied", path.display())),
+        resolution => Ok(resolution)
+    }
+}